@@ -0,0 +1,107 @@
+//! Throughput of short jobs on the pool's lock-free queue compared with the
+//! original `Arc<Mutex<mpsc::Receiver<Job>>>` design, run with `cargo bench`
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_threadpool::{Job, ThreadPool};
+
+const JOBS: usize = 200_000;
+const ROUNDS: usize = 5;
+
+/// The original fixed pool, every worker locks the receiver before each recv
+struct MutexReceiverPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: Option<mpsc::Sender<Job>>,
+}
+
+impl MutexReceiverPool {
+    fn new(size: usize) -> MutexReceiverPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..size)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || loop {
+                    let message = receiver.lock().unwrap().recv();
+                    match message {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        MutexReceiverPool {
+            workers,
+            sender: Some(sender),
+        }
+    }
+
+    fn execute<F>(&self, f: F) where F: FnOnce() + Send + 'static, {
+        let job: Job = Box::new(f);
+        self.sender.as_ref().unwrap().send(job).unwrap();
+    }
+
+    fn join(mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+    }
+}
+
+fn short_job(counter: &Arc<AtomicUsize>) -> impl FnOnce() + Send + 'static {
+    let counter = Arc::clone(counter);
+    move || {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn bench_mutex_receiver(threads: usize) -> Duration {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let pool = MutexReceiverPool::new(threads);
+    let start = Instant::now();
+    for _ in 0..JOBS {
+        pool.execute(short_job(&counter));
+    }
+    pool.join();
+    let elapsed = start.elapsed();
+    assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+    elapsed
+}
+
+fn bench_thread_pool(threads: usize) -> Duration {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let pool = ThreadPool::new(threads).unwrap();
+    let start = Instant::now();
+    for _ in 0..JOBS {
+        pool.execute(short_job(&counter)).unwrap();
+    }
+    pool.shutdown();
+    pool.await_termination(Duration::from_secs(60));
+    let elapsed = start.elapsed();
+    assert_eq!(counter.load(Ordering::Relaxed), JOBS);
+    elapsed
+}
+
+fn best_of(bench: fn(usize) -> Duration, threads: usize) -> Duration {
+    (0..ROUNDS).map(|_| bench(threads)).min().unwrap()
+}
+
+fn main() {
+    println!("{} short jobs, best of {} rounds", JOBS, ROUNDS);
+    println!("{:>8} {:>16} {:>16} {:>8}", "threads", "mutex receiver", "lock-free queue", "speedup");
+    for threads in [1, 2, 4, 8, 16, 32] {
+        let baseline = best_of(bench_mutex_receiver, threads);
+        let current = best_of(bench_thread_pool, threads);
+        println!(
+            "{:>8} {:>14.1}ms {:>14.1}ms {:>7.2}x",
+            threads,
+            baseline.as_secs_f64() * 1000.0,
+            current.as_secs_f64() * 1000.0,
+            baseline.as_secs_f64() / current.as_secs_f64(),
+        );
+    }
+}
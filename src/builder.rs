@@ -0,0 +1,238 @@
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::histogram::Latency;
+use crate::queue::JobQueue;
+use crate::stats::WorkerState;
+use crate::watchdog::{TimeoutHook, Watchdog};
+use crate::{CALLER_RUNS_WORKER, EventSink, HistogramMode, PanicHandler, PoolCreationError, RejectionPolicy, Shared, ThreadPool, Worker};
+
+/// Callback run on a worker thread with the worker id
+pub(crate) type ThreadHook = dyn Fn(usize) + Send + Sync;
+
+/// Callback producing the name of the worker thread with the given id
+pub(crate) type ThreadNamer = dyn Fn(usize) -> String + Send + Sync;
+
+/// Jobs skipped before a lower priority is served out of turn
+const DEFAULT_PRIORITY_AGING: u32 = 32;
+
+/// Builder for a ThreadPool,
+/// configures the worker threads and the hooks called on them
+pub struct ThreadPoolBuilder {
+    num_threads: usize,
+    thread_name: Option<Box<ThreadNamer>>,
+    stack_size: Option<usize>,
+    queue_capacity: Option<usize>,
+    rejection_policy: RejectionPolicy,
+    priority_aging: u32,
+    latency_histograms: Option<HistogramMode>,
+    busy_time: bool,
+    on_thread_start: Option<Box<ThreadHook>>,
+    on_thread_stop: Option<Box<ThreadHook>>,
+    panic_handler: Option<Box<PanicHandler>>,
+    event_sink: Option<Box<dyn EventSink>>,
+    job_timeout: Option<Duration>,
+    on_timeout: Option<Box<TimeoutHook>>,
+    replace_timed_out_workers: bool,
+}
+
+impl ThreadPoolBuilder {
+    /// Create a builder with one worker per available CPU and default thread settings
+    pub fn new() -> ThreadPoolBuilder {
+        let num_threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        ThreadPoolBuilder {
+            num_threads,
+            thread_name: None,
+            stack_size: None,
+            queue_capacity: None,
+            rejection_policy: RejectionPolicy::default(),
+            priority_aging: DEFAULT_PRIORITY_AGING,
+            latency_histograms: None,
+            busy_time: false,
+            on_thread_start: None,
+            on_thread_stop: None,
+            panic_handler: None,
+            event_sink: None,
+            job_timeout: None,
+            on_timeout: None,
+            replace_timed_out_workers: false,
+        }
+    }
+
+    /// Number of worker threads, must be at least one
+    pub fn num_threads(mut self, num_threads: usize) -> ThreadPoolBuilder {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Name worker threads by their id, threads are anonymous by default
+    pub fn thread_name<F>(mut self, thread_name: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.thread_name = Some(Box::new(thread_name));
+        self
+    }
+
+    /// Stack size in bytes of each worker thread
+    pub fn stack_size(mut self, stack_size: usize) -> ThreadPoolBuilder {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Bound the job queue to `capacity` queued jobs, the queue is unbounded by default
+    pub fn queue_capacity(mut self, capacity: usize) -> ThreadPoolBuilder {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// What to do with a job submitted while the bounded queue is full,
+    /// RejectionPolicy::Abort by default
+    pub fn rejection_policy(mut self, policy: RejectionPolicy) -> ThreadPoolBuilder {
+        self.rejection_policy = policy;
+        self
+    }
+
+    /// Number of times a queued job may be passed over for higher priority jobs
+    /// before its priority gets the next pick, 32 by default and zero to never age jobs
+    pub fn priority_aging(mut self, rounds: u32) -> ThreadPoolBuilder {
+        self.priority_aging = rounds;
+        self
+    }
+
+    /// Record queue wait and run time histograms, read with `ThreadPool::latency`,
+    /// pools without histograms do not read the clock when queueing jobs
+    pub fn latency_histograms(mut self, mode: HistogramMode) -> ThreadPoolBuilder {
+        self.latency_histograms = Some(mode);
+        self
+    }
+
+    /// Time every job to report how long each worker spent running jobs in `ThreadPool::stats`,
+    /// off by default so jobs do not read the clock unless something else needs it
+    pub fn busy_time(mut self, enabled: bool) -> ThreadPoolBuilder {
+        self.busy_time = enabled;
+        self
+    }
+
+    /// Called on each worker thread with its id before it takes any job
+    pub fn on_thread_start<F>(mut self, on_thread_start: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_thread_start = Some(Box::new(on_thread_start));
+        self
+    }
+
+    /// Called on each worker thread with its id right before the thread exits
+    pub fn on_thread_stop<F>(mut self, on_thread_stop: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_thread_stop = Some(Box::new(on_thread_stop));
+        self
+    }
+
+    /// Called with the worker id and payload of every panicking job
+    pub fn panic_handler<H>(mut self, handler: H) -> ThreadPoolBuilder
+    where
+        H: Fn(usize, &(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        self.panic_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Report worker and job events to `sink`
+    pub fn event_sink<S>(mut self, sink: S) -> ThreadPoolBuilder
+    where
+        S: EventSink + 'static,
+    {
+        self.event_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Time out every job that runs for longer than `timeout`, jobs have no timeout by default
+    ///
+    /// A watchdog thread reports timed out tasks as TaskError::TimedOut and cancels their token,
+    /// the job itself keeps its worker until it returns
+    pub fn job_timeout(mut self, timeout: Duration) -> ThreadPoolBuilder {
+        self.job_timeout = Some(timeout);
+        self
+    }
+
+    /// Called on the watchdog thread with the worker id and run time of every job that times out
+    pub fn on_timeout<F>(mut self, on_timeout: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize, Duration) + Send + Sync + 'static,
+    {
+        self.on_timeout = Some(Box::new(on_timeout));
+        self
+    }
+
+    /// Spawn a new worker for every worker stuck in a timed out job, the stuck worker
+    /// exits once its job returns so the pool size is restored, off by default
+    pub fn replace_timed_out_workers(mut self, replace: bool) -> ThreadPoolBuilder {
+        self.replace_timed_out_workers = replace;
+        self
+    }
+
+    /// Spawn the workers and create the pool,
+    /// fails if the configuration is invalid or a worker thread cannot be spawned
+    pub fn build(self) -> Result<ThreadPool, PoolCreationError> {
+        if self.num_threads < 1 {
+            return Err(PoolCreationError::new(String::from("Invalid size")));
+        }
+        if self.queue_capacity == Some(0) {
+            return Err(PoolCreationError::new(String::from("Invalid queue capacity")));
+        }
+        let shared = Arc::new(Shared {
+            workers: Mutex::new(Vec::with_capacity(self.num_threads)),
+            next_worker_id: AtomicUsize::new(self.num_threads),
+            num_threads: AtomicUsize::new(self.num_threads),
+            retiring: AtomicUsize::new(0),
+            paused: AtomicBool::new(false),
+            pause_lock: Mutex::new(()),
+            resumed: Condvar::new(),
+            queue: JobQueue::new(self.queue_capacity, self.priority_aging),
+            rejection_policy: self.rejection_policy,
+            shutdown: AtomicBool::new(false),
+            live_workers: AtomicUsize::new(0),
+            caller: WorkerState::new(CALLER_RUNS_WORKER, self.busy_time),
+            submitted: AtomicU64::new(0),
+            retired_completed: AtomicU64::new(0),
+            busy_time: self.busy_time,
+            latency: self.latency_histograms.map(Latency::new),
+            termination_lock: Mutex::new(()),
+            terminated: Condvar::new(),
+            watchdog: Watchdog::new(self.job_timeout, self.replace_timed_out_workers, self.on_timeout),
+            hung_workers: AtomicUsize::new(0),
+            panic_count: AtomicUsize::new(0),
+            panic_handler: self.panic_handler,
+            event_sink: self.event_sink,
+            thread_name: self.thread_name,
+            stack_size: self.stack_size,
+            on_thread_start: self.on_thread_start,
+            on_thread_stop: self.on_thread_stop,
+        });
+        // Workers spawned before a failure are shut down by dropping the partial pool
+        let pool = ThreadPool { shared };
+        for id in 0..self.num_threads {
+            let worker = Worker::spawn(id, Arc::clone(&pool.shared)).map_err(|e| {
+                PoolCreationError::new(format!("Failed to spawn worker {}: {}", id, e))
+            })?;
+            pool.shared.workers.lock().unwrap().push(worker);
+        }
+        if self.job_timeout.is_some() {
+            Watchdog::start(&pool.shared);
+        }
+        Ok(pool)
+    }
+}
+
+impl Default for ThreadPoolBuilder {
+    fn default() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::task;
+use crate::{ExecuteError, Job, PoolCreationError, TaskHandle};
+
+/// Elastic thread pool,
+/// spawns workers on demand up to `max_threads` and retires workers above
+/// `core_threads` once they have been idle for longer than the keep-alive
+#[derive(Debug)]
+pub struct CachedThreadPool {
+    shared: Arc<CachedShared>,
+}
+
+/// State shared between the cached pool and its workers
+struct CachedShared {
+    state: Mutex<CachedState>,
+    job_available: Condvar,
+    all_stopped: Condvar,
+    core_threads: usize,
+    max_threads: usize,
+    keep_alive: Duration,
+    panic_count: AtomicUsize,
+}
+
+struct CachedState {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+    shutdown: bool,
+}
+
+impl CachedThreadPool {
+    /// Create an empty pool, workers are spawned as jobs arrive
+    pub fn new(core_threads: usize, max_threads: usize, keep_alive: Duration) -> Result<CachedThreadPool, PoolCreationError> {
+        if max_threads < 1 || core_threads > max_threads {
+            return Err(PoolCreationError::new(String::from("Invalid size")));
+        }
+        Ok(CachedThreadPool {
+            shared: Arc::new(CachedShared {
+                state: Mutex::new(CachedState {
+                    jobs: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                    shutdown: false,
+                }),
+                job_available: Condvar::new(),
+                all_stopped: Condvar::new(),
+                core_threads,
+                max_threads,
+                keep_alive,
+                panic_count: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    /// Queue a job, handing it to an idle worker or spawning a new one when none is idle,
+    /// once `max_threads` are busy the job waits in the queue
+    pub fn execute<F>(&self, f: F) -> Result<(), ExecuteError<F>> where F: FnOnce() + Send + 'static, {
+        self.dispatch(f, |f| Box::new(f))
+    }
+
+    /// Submit a job whose return value can be collected through the returned TaskHandle
+    pub fn submit<F, T>(&self, f: F) -> Result<TaskHandle<T>, ExecuteError<F>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completer, handle) = task::task();
+        self.dispatch(f, move |f| Box::new(move || completer.run(f)))?;
+        Ok(handle)
+    }
+
+    /// Number of live worker threads
+    pub fn num_threads(&self) -> usize {
+        self.shared.state.lock().unwrap().threads
+    }
+
+    /// Number of worker threads waiting for a job
+    pub fn idle_threads(&self) -> usize {
+        self.shared.state.lock().unwrap().idle
+    }
+
+    /// Number of jobs that have panicked since the pool was created
+    pub fn panic_count(&self) -> usize {
+        self.shared.panic_count.load(Ordering::SeqCst)
+    }
+
+    fn dispatch<F, W>(&self, f: F, wrap: W) -> Result<(), ExecuteError<F>> where W: FnOnce(F) -> Job, {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.shutdown {
+            return Err(ExecuteError::Shutdown(f));
+        }
+        // Every idle worker not already claimed by a queued job can take this one
+        if state.idle <= state.jobs.len() && state.threads < self.shared.max_threads {
+            state.threads += 1;
+            if CachedShared::spawn_worker(&self.shared).is_err() {
+                state.threads -= 1;
+                // Existing workers will get to the job eventually
+                if state.threads == 0 {
+                    return Err(ExecuteError::NoWorkers(f));
+                }
+            }
+        }
+        state.jobs.push_back(wrap(f));
+        drop(state);
+        self.shared.job_available.notify_one();
+        Ok(())
+    }
+}
+
+impl Debug for CachedShared {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedShared")
+            .field("core_threads", &self.core_threads)
+            .field("max_threads", &self.max_threads)
+            .field("keep_alive", &self.keep_alive)
+            .field("panic_count", &self.panic_count)
+            .finish()
+    }
+}
+
+impl CachedShared {
+    /// Spawn a worker, the caller has already counted it in `threads`
+    fn spawn_worker(shared: &Arc<CachedShared>) -> std::io::Result<()> {
+        let shared = Arc::clone(shared);
+        thread::Builder::new().spawn(move || shared.run_worker())?;
+        Ok(())
+    }
+
+    fn run_worker(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                    self.panic_count.fetch_add(1, Ordering::SeqCst);
+                }
+                state = self.state.lock().unwrap();
+                continue;
+            }
+            if state.shutdown {
+                break;
+            }
+            state.idle += 1;
+            let (guard, timeout) = self.job_available.wait_timeout(state, self.keep_alive).unwrap();
+            state = guard;
+            state.idle -= 1;
+            if timeout.timed_out() && state.jobs.is_empty() && state.threads > self.core_threads {
+                break;
+            }
+        }
+        state.threads -= 1;
+        if state.threads == 0 {
+            self.all_stopped.notify_all();
+        }
+    }
+}
+
+/// Graceful shutdown mechanism,
+/// queued jobs are drained before the workers exit
+impl Drop for CachedThreadPool {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.shutdown = true;
+        self.shared.job_available.notify_all();
+        while state.threads > 0 {
+            state = self.shared.all_stopped.wait(state).unwrap();
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Registered tasks kept before the first sweep for finished ones
+const MIN_SWEEP: usize = 16;
+
+/// Shared flag used to cancel tasks cooperatively,
+/// clones observe the same flag
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    tasks: Mutex<Tasks>,
+}
+
+/// Tasks to resolve once the token is cancelled
+#[derive(Default)]
+struct Tasks {
+    tasks: Vec<Weak<dyn OnCancel>>,
+    /// Length at which finished tasks are swept out, doubled after each sweep
+    sweep_at: usize,
+}
+
+/// A task whose handle resolves as soon as its token is cancelled
+pub(crate) trait OnCancel: Send + Sync {
+    fn cancelled(&self);
+
+    fn is_finished(&self) -> bool;
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request cancellation, tasks that have not started are skipped, running tasks
+    /// see it through `is_cancelled` and the handles of both resolve with TaskError::Cancelled
+    pub fn cancel(&self) {
+        if self.state.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let tasks = mem::take(&mut self.state.tasks.lock().unwrap().tasks);
+        for task in tasks.iter().filter_map(Weak::upgrade) {
+            task.cancelled();
+        }
+    }
+
+    /// Check whether cancellation has been requested, long running jobs should poll this
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolve `task` when the token is cancelled, right away if it already is
+    pub(crate) fn register(&self, task: Weak<dyn OnCancel>) {
+        {
+            let mut tasks = self.state.tasks.lock().unwrap();
+            // Checked under the lock so a concurrent `cancel` either sees the task or is seen here
+            if !self.is_cancelled() {
+                // A long lived token would otherwise keep an entry for every task it ever saw
+                if tasks.tasks.len() >= tasks.sweep_at {
+                    tasks.tasks.retain(|task| task.upgrade().is_some_and(|task| !task.is_finished()));
+                    tasks.sweep_at = (tasks.tasks.len() * 2).max(MIN_SWEEP);
+                }
+                tasks.tasks.push(task);
+                return;
+            }
+        }
+        if let Some(task) = task.upgrade() {
+            task.cancelled();
+        }
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::{Debug, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use crate::queue::JobOptions;
+use crate::task::panic_message;
+use crate::{ExecuteError, TaskError, ThreadPool};
+
+/// Submits jobs to a ThreadPool and hands back their results in the order they finish
+pub struct CompletionService<'pool, T> {
+    pool: &'pool ThreadPool,
+    sender: Sender<Completed<T>>,
+    receiver: Receiver<Completed<T>>,
+    submitted: usize,
+    pending: usize,
+}
+
+/// Result of a job submitted through a CompletionService
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completed<T> {
+    /// Position of the job among the jobs submitted to the service, starting at zero
+    pub index: usize,
+    pub result: Result<T, TaskError>,
+}
+
+/// Iterator over the results of a CompletionService in the order the jobs finish
+#[derive(Debug)]
+pub struct Completions<'a, 'pool, T> {
+    service: &'a mut CompletionService<'pool, T>,
+}
+
+/// Sends the result of one job, or TaskError::Discarded if the job is dropped without running
+struct Reporter<T> {
+    index: usize,
+    sender: Option<Sender<Completed<T>>>,
+}
+
+impl<'pool, T> CompletionService<'pool, T> where T: Send + 'static, {
+    pub fn new(pool: &'pool ThreadPool) -> CompletionService<'pool, T> {
+        let (sender, receiver) = mpsc::channel();
+        CompletionService {
+            pool,
+            sender,
+            receiver,
+            submitted: 0,
+            pending: 0,
+        }
+    }
+
+    /// Submit a job, returns the index its result will be reported with
+    pub fn submit<F>(&mut self, f: F) -> Result<usize, ExecuteError<F>> where F: FnOnce() -> T + Send + 'static, {
+        let index = self.submitted;
+        let sender = self.sender.clone();
+        // Only created once the pool accepts the job, a rejected job must not report anything
+        self.pool.dispatch(f, JobOptions::default(), move |f| {
+            let reporter = Reporter {
+                index,
+                sender: Some(sender),
+            };
+            Box::new(move || reporter.run(f))
+        })?;
+        self.submitted += 1;
+        self.pending += 1;
+        Ok(index)
+    }
+
+    /// Number of submitted jobs whose result has not been taken yet
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Take the next finished result without blocking
+    pub fn poll(&mut self) -> Option<Completed<T>> {
+        let completed = self.receiver.try_recv().ok()?;
+        self.pending -= 1;
+        Some(completed)
+    }
+
+    /// Block until the next job finishes, returns None once every result has been taken
+    pub fn take(&mut self) -> Option<Completed<T>> {
+        if self.pending == 0 {
+            return None;
+        }
+        // The service holds a sender, so the channel never disconnects
+        let completed = self.receiver.recv().ok()?;
+        self.pending -= 1;
+        Some(completed)
+    }
+
+    /// Block for at most `timeout` waiting for the next job to finish,
+    /// returns None if nothing finished in time or every result has been taken
+    pub fn poll_timeout(&mut self, timeout: Duration) -> Option<Completed<T>> {
+        if self.pending == 0 {
+            return None;
+        }
+        match self.receiver.recv_timeout(timeout) {
+            Ok(completed) => {
+                self.pending -= 1;
+                Some(completed)
+            }
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Iterate over the results as jobs finish, blocking while jobs are pending,
+    /// ends once every result has been taken
+    pub fn iter(&mut self) -> Completions<'_, 'pool, T> {
+        Completions { service: self }
+    }
+}
+
+impl<T> Iterator for Completions<'_, '_, T> where T: Send + 'static, {
+    type Item = Completed<T>;
+
+    fn next(&mut self) -> Option<Completed<T>> {
+        self.service.take()
+    }
+}
+
+impl<T> Debug for CompletionService<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionService")
+            .field("submitted", &self.submitted)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl<T> Reporter<T> {
+    /// Run the job and send its result, a panic is resumed so the worker still accounts for it
+    fn run<F>(mut self, f: F) where F: FnOnce() -> T, {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(value) => self.send(Ok(value)),
+            Err(payload) => {
+                self.send(Err(TaskError::Panicked(panic_message(payload.as_ref()))));
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    fn send(&mut self, result: Result<T, TaskError>) {
+        if let Some(sender) = self.sender.take() {
+            // The service may have been dropped, nobody is waiting for the result then
+            let _ = sender.send(Completed {
+                index: self.index,
+                result,
+            });
+        }
+    }
+}
+
+impl<T> Drop for Reporter<T> {
+    fn drop(&mut self) {
+        self.send(Err(TaskError::Discarded));
+    }
+}
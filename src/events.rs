@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+/// Structured events emitted by the workers of a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// A worker thread started and is ready to take jobs
+    WorkerStarted { worker: usize },
+    /// A worker picked up a job and is about to run it
+    JobStarted { worker: usize },
+    /// A worker finished running a job, `panicked` is set if the job unwound
+    JobFinished { worker: usize, duration: Duration, panicked: bool },
+    /// A worker thread is exiting
+    WorkerStopped { worker: usize },
+}
+
+/// Receiver of PoolEvents, called on the worker thread that emitted the event
+pub trait EventSink: Send + Sync {
+    fn on_event(&self, event: PoolEvent);
+}
+
+impl<F> EventSink for F where F: Fn(PoolEvent) + Send + Sync, {
+    fn on_event(&self, event: PoolEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Bits of precision kept below the highest set bit, 32 buckets per power of two
+/// keep the relative error of a recorded value under about 3%
+const SUB_BITS: u32 = 5;
+const SUB_COUNT: usize = 1 << SUB_BITS;
+
+/// Enough buckets to cover every u64 nanosecond value
+const BUCKETS: usize = (64 - SUB_BITS as usize + 1) * SUB_COUNT;
+
+/// Whether reading a pool's latency histograms clears them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramMode {
+    /// Histograms accumulate for the lifetime of the pool
+    Cumulative,
+    /// Every read returns the values recorded since the previous read
+    ResetOnRead,
+}
+
+/// Queue wait and run time distributions of a pool, see `ThreadPool::latency`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencySnapshot {
+    /// Time jobs spent in the queue before a worker picked them up
+    pub queue_wait: Histogram,
+    /// Time jobs spent running
+    pub run_time: Histogram,
+}
+
+/// Log-linear histogram of durations recorded with nanosecond resolution,
+/// in the style of HdrHistogram
+#[derive(Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+/// Histogram that can be recorded into concurrently from every worker
+pub(crate) struct AtomicHistogram {
+    counts: Box<[AtomicU64]>,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+/// Latency histograms kept by a pool that has them enabled
+pub(crate) struct Latency {
+    mode: HistogramMode,
+    queue_wait: AtomicHistogram,
+    run_time: AtomicHistogram,
+}
+
+impl Latency {
+    pub(crate) fn new(mode: HistogramMode) -> Latency {
+        Latency {
+            mode,
+            queue_wait: AtomicHistogram::new(),
+            run_time: AtomicHistogram::new(),
+        }
+    }
+
+    pub(crate) fn record(&self, queue_wait: Option<Duration>, run_time: Duration) {
+        if let Some(queue_wait) = queue_wait {
+            self.queue_wait.record(queue_wait);
+        }
+        self.run_time.record(run_time);
+    }
+
+    pub(crate) fn snapshot(&self) -> LatencySnapshot {
+        let reset = self.mode == HistogramMode::ResetOnRead;
+        LatencySnapshot {
+            queue_wait: self.queue_wait.snapshot(reset),
+            run_time: self.run_time.snapshot(reset),
+        }
+    }
+}
+
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_COUNT as u64 {
+        return value as usize;
+    }
+    let shift = 63 - value.leading_zeros() - SUB_BITS;
+    let mantissa = (value >> shift) as usize;
+    (shift as usize + 1) * SUB_COUNT + mantissa - SUB_COUNT
+}
+
+/// Highest value that lands in the bucket at `index`
+fn bucket_upper_bound(index: usize) -> u64 {
+    if index < SUB_COUNT {
+        return index as u64;
+    }
+    let shift = (index / SUB_COUNT - 1) as u32;
+    let mantissa = (SUB_COUNT + index % SUB_COUNT) as u64;
+    ((mantissa + 1) << shift).wrapping_sub(1)
+}
+
+impl AtomicHistogram {
+    pub(crate) fn new() -> AtomicHistogram {
+        AtomicHistogram {
+            counts: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Copy the recorded values, clearing them if `reset` is set
+    pub(crate) fn snapshot(&self, reset: bool) -> Histogram {
+        let read = |value: &AtomicU64| {
+            if reset {
+                value.swap(0, Ordering::Relaxed)
+            } else {
+                value.load(Ordering::Relaxed)
+            }
+        };
+        let counts: Vec<u64> = self.counts.iter().map(read).collect();
+        let count = counts.iter().sum();
+        Histogram {
+            counts,
+            count,
+            sum: read(&self.sum),
+            max: read(&self.max),
+        }
+    }
+}
+
+impl Histogram {
+    /// Number of recorded values
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Largest recorded value
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    /// Average of the recorded values
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(self.sum / self.count)
+    }
+
+    /// Sum of the recorded values
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum)
+    }
+
+    /// Number of recorded values at or below `value`, up to the precision of the buckets
+    pub fn count_at_or_below(&self, value: Duration) -> u64 {
+        let nanos = u64::try_from(value.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[..=bucket_index(nanos)].iter().sum()
+    }
+
+    /// Value at or below which `percentile` percent of the recorded values fall,
+    /// e.g. `percentile(99.9)`, zero if nothing was recorded
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let fraction = percentile.clamp(0.0, 100.0) / 100.0;
+        let rank = ((fraction * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_nanos(bucket_upper_bound(index).min(self.max));
+            }
+        }
+        Duration::from_nanos(self.max)
+    }
+
+    pub fn p50(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    pub fn p99(&self) -> Duration {
+        self.percentile(99.0)
+    }
+
+    pub fn p999(&self) -> Duration {
+        self.percentile(99.9)
+    }
+}
+
+impl Debug for Histogram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Histogram")
+            .field("count", &self.count)
+            .field("mean", &self.mean())
+            .field("p50", &self.p50())
+            .field("p99", &self.p99())
+            .field("p999", &self.p999())
+            .field("max", &self.max())
+            .finish()
+    }
+}
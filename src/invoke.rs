@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+
+use crate::task::{self, panic_message};
+use crate::{CancellationToken, TaskError, TaskHandle, ThreadPool};
+
+/// Error returned by `ThreadPool::invoke_any` when no task succeeded,
+/// carries the error of every task in submission order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateError {
+    errors: Vec<TaskError>,
+}
+
+impl AggregateError {
+    /// Errors of the failed tasks, in submission order
+    pub fn errors(&self) -> &[TaskError] {
+        &self.errors
+    }
+
+    pub fn into_errors(self) -> Vec<TaskError> {
+        self.errors
+    }
+}
+
+impl Display for AggregateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.errors.first() {
+            Some(first) => write!(f, "all {} tasks failed, first error: {}", self.errors.len(), first),
+            None => write!(f, "no tasks to invoke"),
+        }
+    }
+}
+
+impl Error for AggregateError {}
+
+impl ThreadPool {
+    /// Run every task on the pool and wait for all of them,
+    /// returns their results in submission order
+    ///
+    /// A task the pool rejects runs on the calling thread instead
+    pub fn invoke_all<I, F, T>(&self, tasks: I) -> Vec<Result<T, TaskError>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let handles: Vec<TaskHandle<T>> = tasks
+            .into_iter()
+            .map(|f| match self.submit(f) {
+                Ok(handle) => handle,
+                Err(rejected) => {
+                    let (completer, handle) = task::task();
+                    // The panic is already recorded in the handle
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| completer.run(rejected.into_inner())));
+                    handle
+                }
+            })
+            .collect();
+        handles.into_iter().map(TaskHandle::join).collect()
+    }
+
+    /// Run every task on the pool and return the result of the first one to finish without panicking,
+    /// the shared token is then cancelled so tasks that have not started are skipped
+    /// and running tasks can stop early
+    ///
+    /// A task the pool rejects runs on the calling thread instead
+    pub fn invoke_any<I, F, T>(&self, tasks: I) -> Result<T, AggregateError>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce(&CancellationToken) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let (sender, receiver) = mpsc::channel();
+        let mut count = 0;
+        for f in tasks {
+            let index = count;
+            count += 1;
+            let sender = sender.clone();
+            let token = token.clone();
+            let job = move || {
+                if token.is_cancelled() {
+                    let _ = sender.send((index, Err(TaskError::Cancelled)));
+                    return;
+                }
+                match panic::catch_unwind(AssertUnwindSafe(|| f(&token))) {
+                    Ok(value) => {
+                        let _ = sender.send((index, Ok(value)));
+                    }
+                    Err(payload) => {
+                        let _ = sender.send((index, Err(TaskError::Panicked(panic_message(payload.as_ref())))));
+                        panic::resume_unwind(payload);
+                    }
+                }
+            };
+            if let Err(rejected) = self.execute(job) {
+                let _ = panic::catch_unwind(AssertUnwindSafe(rejected.into_inner()));
+            }
+        }
+        // Once every job has reported or been dropped the channel disconnects
+        drop(sender);
+        let mut errors: Vec<Option<TaskError>> = vec![None; count];
+        for (index, result) in receiver.iter() {
+            match result {
+                Ok(value) => {
+                    token.cancel();
+                    return Ok(value);
+                }
+                Err(error) => errors[index] = Some(error),
+            }
+        }
+        let errors = errors.into_iter().map(|error| error.unwrap_or(TaskError::Discarded)).collect();
+        Err(AggregateError { errors })
+    }
+}
@@ -0,0 +1,145 @@
+use std::cell::Cell;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::queue::JobOptions;
+use crate::stats::WorkerState;
+use crate::{Job, RejectionPolicy, Shared, ThreadPool};
+
+/// Rounds of yielding before a helping worker with nothing to run waits on the joined job
+const HELP_YIELD_ROUNDS: u32 = 16;
+
+/// How long a helping worker waits on the joined job before checking the queue again
+const HELP_POLL: Duration = Duration::from_millis(1);
+
+thread_local! {
+    /// Pool and counters of the worker running on this thread, set for the lifetime of the worker
+    static CURRENT: Cell<Option<(*const Shared, *const WorkerState)>> = const { Cell::new(None) };
+    /// Set when a job run while helping timed out and the worker has been replaced
+    static REPLACED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Closure `ThreadPool::join` queued and its result, whoever takes the closure first runs it,
+/// either a worker or the joining thread once `a` is done
+struct JoinPacket<F, R> {
+    state: Mutex<JoinState<F, R>>,
+    done: Condvar,
+}
+
+struct JoinState<F, R> {
+    f: Option<F>,
+    result: Option<thread::Result<R>>,
+    finished: bool,
+}
+
+/// Mark the calling thread as a worker of `shared` until `leave_worker` is called
+pub(crate) fn enter_worker(shared: &Shared, worker: &WorkerState) {
+    CURRENT.with(|current| current.set(Some((shared as *const Shared, worker as *const WorkerState))));
+}
+
+pub(crate) fn leave_worker() {
+    CURRENT.with(|current| current.set(None));
+}
+
+/// Check whether a job run while helping asked the calling worker to retire
+pub(crate) fn take_replaced() -> bool {
+    REPLACED.with(|replaced| replaced.replace(false))
+}
+
+impl ThreadPool {
+    /// Run `a` on the calling thread and `b` on the pool and return both results,
+    /// both closures may borrow from the caller's stack
+    ///
+    /// Called from one of the pool's own workers, the worker runs other queued jobs while `b`
+    /// is pending instead of blocking, so recursive joins do not deadlock the pool.
+    /// If `a` or `b` panics the panic is resumed once both have finished, `a`'s first
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB + Send,
+        RB: Send,
+    {
+        let packet = Arc::new(JoinPacket {
+            state: Mutex::new(JoinState {
+                f: Some(b),
+                result: None,
+                finished: false,
+            }),
+            done: Condvar::new(),
+        });
+        let queued = Arc::clone(&packet);
+        let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || queued.run());
+        // SAFETY: this function does not return before `b` has been taken out of the packet and run,
+        // a queued job that is still around afterwards finds the packet empty and touches no borrows
+        let job: Job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+        // Blocking for room would deadlock a worker joining from inside the pool and evicting would
+        // drop an unrelated job, so a full queue rejects the job whatever the pool's policy.
+        // A rejected job is dropped right away, `b` is then run below like an unclaimed one
+        let _ = self.dispatch_with_policy(RejectionPolicy::Abort, job, JobOptions::default(), |job| job);
+        let result_a = panic::catch_unwind(AssertUnwindSafe(a));
+        // Running `b` here if no worker got to it keeps recursive joins as deep as the sequential recursion
+        packet.run();
+        self.wait_joined(&packet);
+        let result_b = packet.state.lock().unwrap().result.take().unwrap();
+        match (result_a, result_b) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(payload), _) | (_, Err(payload)) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Wait for the joined job, running other queued jobs meanwhile if called from a worker of this pool
+    fn wait_joined<F, R>(&self, packet: &JoinPacket<F, R>) {
+        let shared = &*self.shared;
+        let worker = CURRENT.with(Cell::get).and_then(|(pool, worker)| {
+            // SAFETY: CURRENT is only set while the worker thread keeps its pool and counters alive
+            ptr::eq(pool, shared).then(|| unsafe { &*worker })
+        });
+        let mut round = 0;
+        loop {
+            if packet.state.lock().unwrap().finished {
+                return;
+            }
+            if let Some(worker) = worker {
+                if let Some(job) = shared.queue.try_pop(|| shared.is_paused()) {
+                    if shared.run_job(worker, job) {
+                        REPLACED.with(|replaced| replaced.set(true));
+                    }
+                    round = 0;
+                    continue;
+                }
+            }
+            let state = packet.state.lock().unwrap();
+            if state.finished {
+                return;
+            }
+            match worker {
+                Some(_) if round < HELP_YIELD_ROUNDS => {
+                    drop(state);
+                    thread::yield_now();
+                    round += 1;
+                }
+                // Pushes do not wake a helping worker, so it checks the queue again now and then
+                Some(_) => drop(packet.done.wait_timeout(state, HELP_POLL).unwrap()),
+                None => drop(packet.done.wait(state).unwrap()),
+            }
+        }
+    }
+}
+
+impl<F, R> JoinPacket<F, R> where F: FnOnce() -> R, {
+    /// Run the closure unless someone else already took it
+    fn run(&self) {
+        let f = self.state.lock().unwrap().f.take();
+        if let Some(f) = f {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.finished = true;
+            self.done.notify_all();
+        }
+    }
+}
@@ -1,25 +1,71 @@
+use std::any::Any;
 use std::error::Error;
-use std::fmt::{Display, Formatter};
-use std::sync::{Arc, mpsc, Mutex};
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-type Job = Box<dyn FnOnce() + Send + 'static>;
+mod builder;
+mod cached;
+mod cancel;
+mod completion;
+mod events;
+mod histogram;
+mod invoke;
+mod join;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+mod parallel;
+mod queue;
+mod scheduled;
+mod scope;
+mod stats;
+mod task;
+mod watchdog;
+mod work_stealing;
+
+use builder::{ThreadHook, ThreadNamer};
+use histogram::Latency;
+use queue::{JobOptions, JobQueue, QueuedJob, Reserve};
+use stats::WorkerState;
+use watchdog::Watchdog;
+
+pub use builder::ThreadPoolBuilder;
+pub use cached::CachedThreadPool;
+pub use cancel::CancellationToken;
+pub use completion::{Completed, Completions, CompletionService};
+pub use events::{EventSink, PoolEvent};
+pub use histogram::{Histogram, HistogramMode, LatencySnapshot};
+pub use invoke::AggregateError;
+pub use parallel::ParallelSource;
+pub use queue::{Priority, RejectionPolicy};
+pub use scheduled::{ScheduledHandle, ScheduledThreadPool};
+pub use scope::Scope;
+pub use stats::{PoolStats, WorkerStats};
+pub use task::{TaskError, TaskHandle};
+pub use work_stealing::WorkStealingPool;
+
+/// A unit of work queued on a pool
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
 
 /// TheadPool struct,
-/// contains vector of worker threads and a sender channel
+/// contains the state shared with the worker threads, including the workers and the job queue
 #[derive(Debug)]
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>,
+    shared: Arc<Shared>,
 }
 
 
 /// Worker struct for the fixed thread pool
-/// contains a thread id and a thread handle definition
+/// contains the thread handle definition and the counters shared with the thread
 #[derive(Debug)]
 struct Worker {
-    id: usize,
     thread: Option<thread::JoinHandle<()>>,
+    state: Arc<WorkerState>,
 }
 
 /// Error in case of pool creation
@@ -30,7 +76,7 @@ pub struct PoolCreationError {
 
 impl PoolCreationError {
     pub fn new(message: String) -> PoolCreationError{
-        return PoolCreationError {
+        PoolCreationError {
             message
         }
     }
@@ -48,66 +94,638 @@ impl Error for PoolCreationError {
     }
 }
 
+/// Error in case a job is rejected by the pool,
+/// hands the rejected job back to the caller
+pub enum ExecuteError<F> {
+    /// The pool has been shut down and no longer accepts jobs
+    Shutdown(F),
+    /// The job queue is at capacity
+    QueueFull(F),
+    /// Every worker of the pool has died
+    NoWorkers(F),
+}
+
+impl<F> ExecuteError<F> {
+    /// Take back the rejected job
+    pub fn into_inner(self) -> F {
+        match self {
+            ExecuteError::Shutdown(f) | ExecuteError::QueueFull(f) | ExecuteError::NoWorkers(f) => f,
+        }
+    }
+}
+
+impl<F> Debug for ExecuteError<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteError::Shutdown(_) => write!(f, "Shutdown(..)"),
+            ExecuteError::QueueFull(_) => write!(f, "QueueFull(..)"),
+            ExecuteError::NoWorkers(_) => write!(f, "NoWorkers(..)"),
+        }
+    }
+}
+
+impl<F> Display for ExecuteError<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteError::Shutdown(_) => write!(f, "pool has been shut down"),
+            ExecuteError::QueueFull(_) => write!(f, "job queue is full"),
+            ExecuteError::NoWorkers(_) => write!(f, "pool has no live workers"),
+        }
+    }
+}
+
+impl<F> Error for ExecuteError<F> {}
+
+/// Worker id reported to panic handlers and event sinks for jobs a full pool runs
+/// on the submitting thread under RejectionPolicy::CallerRuns
+pub const CALLER_RUNS_WORKER: usize = usize::MAX;
+
+/// Callback invoked with the worker id and the payload whenever a job panics
+pub type PanicHandler = dyn Fn(usize, &(dyn Any + Send)) + Send + Sync;
+
+/// State shared between the pool and its workers
+struct Shared {
+    workers: Mutex<Vec<Worker>>,
+    next_worker_id: AtomicUsize,
+    /// Number of workers the pool is meant to have, see `ThreadPool::set_num_threads`
+    num_threads: AtomicUsize,
+    /// Workers asked to exit the next time they look for a job
+    retiring: AtomicUsize,
+    paused: AtomicBool,
+    pause_lock: Mutex<()>,
+    resumed: Condvar,
+    queue: JobQueue,
+    rejection_policy: RejectionPolicy,
+    shutdown: AtomicBool,
+    live_workers: AtomicUsize,
+    /// Counters of the jobs run on the submitting thread under RejectionPolicy::CallerRuns
+    caller: WorkerState,
+    /// Jobs accepted into the queue or run by the caller
+    submitted: AtomicU64,
+    /// Jobs completed by workers no longer listed in `workers`
+    retired_completed: AtomicU64,
+    /// Whether workers time every job, see `ThreadPoolBuilder::busy_time`
+    busy_time: bool,
+    latency: Option<Latency>,
+    termination_lock: Mutex<()>,
+    terminated: Condvar,
+    watchdog: Watchdog,
+    /// Workers stuck in a job that timed out, not waited for when the pool is dropped
+    hung_workers: AtomicUsize,
+    panic_count: AtomicUsize,
+    panic_handler: Option<Box<PanicHandler>>,
+    event_sink: Option<Box<dyn EventSink>>,
+    thread_name: Option<Box<ThreadNamer>>,
+    stack_size: Option<usize>,
+    on_thread_start: Option<Box<ThreadHook>>,
+    on_thread_stop: Option<Box<ThreadHook>>,
+}
+
+impl Shared {
+    /// Count a panicked job and hand its payload to the panic handler,
+    /// a panicking handler is swallowed so it cannot take the worker down
+    fn handle_panic(&self, id: usize, payload: Box<dyn Any + Send>) {
+        self.panic_count.fetch_add(1, Ordering::SeqCst);
+        if let Some(handler) = self.panic_handler.as_ref() {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(id, payload.as_ref())));
+        }
+    }
+
+    /// Run one job on behalf of `worker`, keeping the counters and events up to date,
+    /// returns true if the job timed out and the worker has been replaced
+    fn run_job(&self, worker: &WorkerState, queued: QueuedJob) -> bool {
+        let id = worker.id;
+        worker.job_started();
+        self.emit(PoolEvent::JobStarted { worker: id });
+        // A job run on the submitting thread holds up no worker, so it is not timed out
+        let timeout = queued.options.timeout.or(self.watchdog.default_timeout).filter(|_| id != CALLER_RUNS_WORKER);
+        // Only read the clock when someone is listening
+        let timed = timeout.is_some()
+            || self.latency.is_some()
+            || self.event_sink.is_some()
+            || self.busy_time;
+        let started = timed.then(Instant::now);
+        let watched = started
+            .zip(timeout)
+            .map(|(started, timeout)| self.watchdog.watch(id, started, timeout, queued.options.control));
+        // A panicking job must not unwind through the worker loop
+        let result = panic::catch_unwind(AssertUnwindSafe(queued.job));
+        let duration = started.map(|started| started.elapsed());
+        let (timed_out, replaced) = match watched {
+            Some(key) => self.watchdog.unwatch(key),
+            None => (false, false),
+        };
+        if timed_out {
+            self.hung_workers.fetch_sub(1, Ordering::SeqCst);
+        }
+        if let (Some(latency), Some(started), Some(duration)) = (self.latency.as_ref(), started, duration) {
+            let queue_wait = queued.enqueued.map(|enqueued| started.saturating_duration_since(enqueued));
+            latency.record(queue_wait, duration);
+        }
+        worker.job_finished(duration);
+        self.emit(PoolEvent::JobFinished {
+            worker: id,
+            duration: duration.unwrap_or_default(),
+            panicked: result.is_err(),
+        });
+        if let Err(payload) = result {
+            self.handle_panic(id, payload);
+        }
+        self.queue.finish();
+        replaced
+    }
+
+    /// Claim one pending retirement, true if the calling worker should exit
+    fn try_retire(&self) -> bool {
+        self.retiring
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |retiring| retiring.checked_sub(1))
+            .is_ok()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Park a worker until the pool is resumed or a worker is asked to retire
+    fn wait_while_paused(&self) {
+        let mut guard = self.pause_lock.lock().unwrap();
+        while self.is_paused() && self.retiring.load(Ordering::SeqCst) == 0 {
+            guard = self.resumed.wait(guard).unwrap();
+        }
+    }
+
+    /// Wake workers parked by a pause
+    fn wake_paused(&self) {
+        let _guard = self.pause_lock.lock().unwrap();
+        self.resumed.notify_all();
+    }
+
+    /// Drop the workers whose thread has exited, keeping their completed jobs in the pool totals
+    fn forget_exited(&self, workers: &mut Vec<Worker>) {
+        workers.retain(|worker| {
+            let exited = worker.thread.as_ref().is_none_or(|thread| thread.is_finished());
+            if exited {
+                self.retired_completed.fetch_add(worker.state.completed(), Ordering::Relaxed);
+            }
+            !exited
+        });
+    }
+
+    /// Wake threads waiting for the workers to exit
+    fn notify_terminated(&self) {
+        let _guard = self.termination_lock.lock().unwrap();
+        self.terminated.notify_all();
+    }
+
+    /// Report an event to the sink, a panicking sink is swallowed like a panicking panic handler
+    fn emit(&self, event: PoolEvent) {
+        if let Some(sink) = self.event_sink.as_ref() {
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| sink.on_event(event)));
+        }
+    }
+}
+
+impl Debug for Shared {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("rejection_policy", &self.rejection_policy)
+            .field("shutdown", &self.shutdown)
+            .field("live_workers", &self.live_workers)
+            .field("panic_count", &self.panic_count)
+            .field("panic_handler", &self.panic_handler.is_some())
+            .field("event_sink", &self.event_sink.is_some())
+            .field("latency", &self.latency.is_some())
+            .field("stack_size", &self.stack_size)
+            .finish()
+    }
+}
+
+/// Decrements the live worker count when a worker thread exits,
+/// waking await_termination and the pool's Drop
+struct LiveGuard(Arc<Shared>);
+
+impl Drop for LiveGuard {
+    fn drop(&mut self) {
+        self.0.live_workers.fetch_sub(1, Ordering::SeqCst);
+        self.0.notify_terminated();
+    }
+}
+
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
-
-            match message {
-                Ok(job) => {
-                    println!("Worker {id} got a job; executing.");
-                    job();
+    fn spawn(id: usize, shared: Arc<Shared>) -> io::Result<Worker> {
+        let mut builder = thread::Builder::new();
+        if let Some(thread_name) = shared.thread_name.as_ref() {
+            builder = builder.name(thread_name(id));
+        }
+        if let Some(stack_size) = shared.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        let state = Arc::new(WorkerState::new(id, shared.busy_time));
+        let worker_state = Arc::clone(&state);
+        // If spawning fails the closure is dropped and the guard undoes the count
+        shared.live_workers.fetch_add(1, Ordering::SeqCst);
+        let guard = LiveGuard(shared);
+        let thread = builder.spawn(move || {
+            let shared = &guard.0;
+            // A panicking hook must not take the worker down before it has counted itself out
+            if let Some(on_thread_start) = shared.on_thread_start.as_ref() {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| on_thread_start(id)));
+            }
+            shared.emit(PoolEvent::WorkerStarted { worker: id });
+            join::enter_worker(shared, &worker_state);
+            loop {
+                if shared.try_retire() {
+                    break;
+                }
+                if shared.is_paused() {
+                    shared.wait_while_paused();
+                    continue;
                 }
-                Err(_) => {
-                    println!("Worker {id} disconnected; shutting down.");
+                let message = shared.queue.pop(|| shared.is_paused() || shared.retiring.load(Ordering::SeqCst) > 0);
+
+                let exit = match message {
+                    // A replaced worker leaves the pool once its hung job returns
+                    Some(job) => shared.run_job(&worker_state, job) | join::take_replaced(),
+                    // Otherwise the pool was paused or asked a worker to retire
+                    None => shared.queue.is_drained(),
+                };
+                if exit {
                     break;
                 }
             }
-        });
+            join::leave_worker();
+            shared.emit(PoolEvent::WorkerStopped { worker: id });
+            if let Some(on_thread_stop) = shared.on_thread_stop.as_ref() {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| on_thread_stop(id)));
+            }
+        })?;
 
-        Worker {
-            id,
+        Ok(Worker {
             thread: Some(thread),
+            state,
+        })
+    }
+
+    /// Spawn a worker taking the place of one stuck in a job that timed out
+    fn spawn_replacement(shared: &Arc<Shared>) {
+        let id = shared.next_worker_id.fetch_add(1, Ordering::SeqCst);
+        // Capacity is only restored on a best effort basis
+        if let Ok(worker) = Worker::spawn(id, Arc::clone(shared)) {
+            let mut workers = shared.workers.lock().unwrap();
+            shared.forget_exited(&mut workers);
+            workers.push(worker);
         }
     }
 }
 
 impl ThreadPool {
     pub fn new(size: usize) -> Result<ThreadPool,PoolCreationError> {
-        if size < 1 {
-            return Err(PoolCreationError {
-                message: String::from("Invalid size")
-            })
-        }
-        let (sender, receiver) = mpsc::channel();
-        let receiver = Arc::new(Mutex::new(receiver));
-        let mut workers = Vec::with_capacity(size);
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
-        }
-        Ok(ThreadPool {
+        ThreadPoolBuilder::new().num_threads(size).build()
+    }
+
+    /// Create a pool whose `handler` is called with the worker id and payload of every panicking job
+    pub fn with_panic_handler<H>(size: usize, handler: H) -> Result<ThreadPool, PoolCreationError>
+    where
+        H: Fn(usize, &(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        ThreadPoolBuilder::new().num_threads(size).panic_handler(handler).build()
+    }
+
+    /// Create a pool that reports worker and job events to `sink`
+    pub fn with_event_sink<S>(size: usize, sink: S) -> Result<ThreadPool, PoolCreationError>
+    where
+        S: EventSink + 'static,
+    {
+        ThreadPoolBuilder::new().num_threads(size).event_sink(sink).build()
+    }
+
+    /// Start configuring a pool with a ThreadPoolBuilder
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+
+    /// Number of jobs that have panicked since the pool was created
+    pub fn panic_count(&self) -> usize {
+        self.shared.panic_count.load(Ordering::SeqCst)
+    }
+
+    /// Number of worker threads the pool is sized to,
+    /// retiring workers may still be finishing their job
+    pub fn num_threads(&self) -> usize {
+        self.shared.num_threads.load(Ordering::SeqCst)
+    }
+
+    /// Resize the pool to `num_threads` workers, growing spawns new workers right away
+    /// and shrinking retires workers as they finish their current job, running jobs are never interrupted
+    pub fn set_num_threads(&self, num_threads: usize) -> Result<(), PoolCreationError> {
+        if num_threads < 1 {
+            return Err(PoolCreationError::new(String::from("Invalid size")));
+        }
+        if self.is_shutdown() {
+            return Err(PoolCreationError::new(String::from("Pool has been shut down")));
+        }
+        let mut workers = self.shared.workers.lock().unwrap();
+        self.shared.forget_exited(&mut workers);
+        let current = self.shared.num_threads.load(Ordering::SeqCst);
+        if num_threads < current {
+            self.shared.retiring.fetch_add(current - num_threads, Ordering::SeqCst);
+            self.shared.num_threads.store(num_threads, Ordering::SeqCst);
+            self.shared.queue.unpark_all();
+            self.shared.wake_paused();
+            return Ok(());
+        }
+        // Workers that have not retired yet are kept instead of spawning new ones
+        let mut missing = num_threads - current;
+        while missing > 0 && self.shared.try_retire() {
+            missing -= 1;
+            self.shared.num_threads.fetch_add(1, Ordering::SeqCst);
+        }
+        for _ in 0..missing {
+            let id = self.shared.next_worker_id.fetch_add(1, Ordering::SeqCst);
+            let worker = Worker::spawn(id, Arc::clone(&self.shared))
+                .map_err(|e| PoolCreationError::new(format!("Failed to spawn worker {}: {}", id, e)))?;
+            workers.push(worker);
+            self.shared.num_threads.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the pool's counters, cheap enough to poll from a monitoring thread
+    pub fn stats(&self) -> PoolStats {
+        let shared = &self.shared;
+        let queued = shared.queue.len();
+        let workers: Vec<WorkerStats> = {
+            let mut workers = shared.workers.lock().unwrap();
+            shared.forget_exited(&mut workers);
+            workers.iter().map(|worker| worker.state.snapshot()).collect()
+        };
+        let completed = workers.iter().map(|worker| worker.completed).sum::<u64>()
+            + shared.caller.completed()
+            + shared.retired_completed.load(Ordering::Relaxed);
+        let live = shared.live_workers.load(Ordering::SeqCst);
+        let active = workers.iter().filter(|worker| worker.busy).count().min(live);
+        PoolStats {
+            queued,
+            active_workers: active,
+            idle_workers: live - active,
+            submitted: shared.submitted.load(Ordering::Relaxed),
+            completed,
+            panicked: shared.panic_count.load(Ordering::SeqCst) as u64,
             workers,
-            sender: Some(sender),
-        })
+        }
+    }
+
+    /// Queue wait and run time histograms,
+    /// None unless enabled with `ThreadPoolBuilder::latency_histograms`
+    pub fn latency(&self) -> Option<LatencySnapshot> {
+        self.shared.latency.as_ref().map(Latency::snapshot)
+    }
+
+    /// Queue a job for execution,
+    /// the job is handed back inside the error if the pool rejects it
+    pub fn execute<F>(&self, f: F) -> Result<(), ExecuteError<F>> where F: FnOnce() + Send + 'static, {
+        self.dispatch(f, JobOptions::default(), |f| Box::new(f))
+    }
+
+    /// Queue a job ahead of every job of a lower priority,
+    /// jobs of the same priority run in the order they were queued
+    pub fn execute_with_priority<F>(&self, priority: Priority, f: F) -> Result<(), ExecuteError<F>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let options = JobOptions {
+            priority,
+            ..JobOptions::default()
+        };
+        self.dispatch(f, options, |f| Box::new(f))
+    }
+
+    /// Submit a job whose return value can be collected through the returned TaskHandle,
+    /// a panic inside the job is reported as TaskError::Panicked
+    pub fn submit<F, T>(&self, f: F) -> Result<TaskHandle<T>, ExecuteError<F>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completer, handle) = task::task();
+        let options = JobOptions {
+            control: self.shared.watchdog.default_timeout.map(|_| completer.control(None)),
+            ..JobOptions::default()
+        };
+        self.dispatch(f, options, move |f| Box::new(move || completer.run(f)))?;
+        Ok(handle)
+    }
+
+    /// Submit a job that can be cancelled through `token`,
+    /// the job is skipped if the token is cancelled before it starts and can poll the token while running
+    ///
+    /// The handle resolves with TaskError::Cancelled as soon as the token is cancelled unless the job
+    /// finished first, a job that is already running keeps its worker until it returns
+    pub fn submit_with_token<F, T>(&self, token: CancellationToken, f: F) -> Result<TaskHandle<T>, ExecuteError<F>>
+    where
+        F: FnOnce(&CancellationToken) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completer, handle) = task::task();
+        let options = JobOptions {
+            control: self.shared.watchdog.default_timeout.map(|_| completer.control(Some(token.clone()))),
+            ..JobOptions::default()
+        };
+        self.dispatch(f, options, move |f| {
+            completer.cancel_with(&token);
+            Box::new(move || completer.run_cancellable(token, f))
+        })?;
+        Ok(handle)
+    }
+
+    /// Submit a job that is timed out once it has run for longer than `timeout`,
+    /// overriding the pool's default job timeout
+    ///
+    /// A timed out job is reported as TaskError::TimedOut and its token is cancelled,
+    /// the job keeps its worker until it returns, so it should poll the token
+    pub fn submit_with_timeout<F, T>(&self, timeout: Duration, f: F) -> Result<TaskHandle<T>, ExecuteError<F>>
+    where
+        F: FnOnce(&CancellationToken) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let token = CancellationToken::new();
+        let (completer, handle) = task::task();
+        let options = JobOptions {
+            timeout: Some(timeout),
+            control: Some(completer.control(Some(token.clone()))),
+            ..JobOptions::default()
+        };
+        Watchdog::start(&self.shared);
+        self.dispatch(f, options, move |f| Box::new(move || completer.run_cancellable(token, f)))?;
+        Ok(handle)
     }
 
-    pub fn execute<F>(&self, f: F) where F: FnOnce() + Send + 'static, {
-        let job = Box::new(f);
-        self.sender.as_ref().unwrap().send(job).unwrap();
+    /// Stop accepting jobs, the workers finish everything already queued and then exit
+    pub fn shutdown(&self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.queue.close();
+    }
+
+    /// Stop accepting jobs and take back every job that has not started yet,
+    /// jobs already running are left to finish
+    pub fn shutdown_now(&self) -> Vec<Job> {
+        self.shutdown();
+        self.shared.queue.drain().into_iter().map(|queued| queued.job).collect()
+    }
+
+    /// Stop workers from starting new jobs, queued jobs stay queued and jobs already running finish,
+    /// see `await_in_flight` to wait for them
+    ///
+    /// A paused pool keeps accepting jobs, and does not drain its queue on shutdown
+    /// or go idle for `wait_idle` until resumed
+    pub fn pause(&self) {
+        self.shared.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Let the workers take jobs again
+    pub fn resume(&self) {
+        self.shared.paused.store(false, Ordering::SeqCst);
+        self.shared.wake_paused();
+    }
+
+    /// Check whether the pool is paused
+    pub fn is_paused(&self) -> bool {
+        self.shared.is_paused()
+    }
+
+    /// Block until no worker is running a job or `timeout` elapses, returns true if the workers went quiet,
+    /// after `pause` no new job starts so this waits for the jobs that were already running
+    pub fn await_in_flight(&self, timeout: Duration) -> bool {
+        self.shared.queue.wait_quiet(false, Some(Instant::now() + timeout))
+    }
+
+    /// Block until the queue is empty and no worker is running a job,
+    /// jobs submitted meanwhile are waited for as well
+    ///
+    /// Queued jobs do not start while the pool is paused, so on a paused pool with jobs queued
+    /// this only returns once the pool is resumed, use `await_in_flight` to wait for the running jobs.
+    /// Calling this from one of the pool's own jobs deadlocks
+    pub fn wait_idle(&self) {
+        self.shared.queue.wait_quiet(true, None);
+    }
+
+    /// Like `wait_idle`, but gives up after `timeout`, returns true if the pool went idle,
+    /// a paused pool with jobs queued never does
+    pub fn wait_idle_timeout(&self, timeout: Duration) -> bool {
+        self.shared.queue.wait_quiet(true, Some(Instant::now() + timeout))
+    }
+
+    /// Check whether shutdown has been requested
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Check whether the pool has been shut down and every worker has exited
+    pub fn is_terminated(&self) -> bool {
+        self.is_shutdown() && self.shared.live_workers.load(Ordering::SeqCst) == 0
+    }
+
+    /// Block until every worker has exited after a shutdown or `timeout` elapses,
+    /// returns true if the pool terminated
+    pub fn await_termination(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.shared.termination_lock.lock().unwrap();
+        while !self.is_terminated() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            guard = self.shared.terminated.wait_timeout(guard, deadline - now).unwrap().0;
+        }
+        true
+    }
+
+    /// Check the pool can accept a job before boxing it,
+    /// so a rejected `f` can be returned unchanged
+    fn dispatch<F, W>(&self, f: F, options: JobOptions, wrap: W) -> Result<(), ExecuteError<F>>
+    where
+        W: FnOnce(F) -> Job,
+    {
+        self.dispatch_with_policy(self.shared.rejection_policy, f, options, wrap)
+    }
+
+    /// Like `dispatch`, but a full queue is handled by `policy` rather than the pool's own policy
+    fn dispatch_with_policy<F, W>(
+        &self,
+        policy: RejectionPolicy,
+        f: F,
+        options: JobOptions,
+        wrap: W,
+    ) -> Result<(), ExecuteError<F>>
+    where
+        W: FnOnce(F) -> Job,
+    {
+        if self.shared.shutdown.load(Ordering::SeqCst) {
+            return Err(ExecuteError::Shutdown(f));
+        }
+        if self.shared.live_workers.load(Ordering::SeqCst) == 0 {
+            return Err(ExecuteError::NoWorkers(f));
+        }
+        let slot = match policy {
+            RejectionPolicy::Block => self.shared.queue.reserve(true),
+            RejectionPolicy::DiscardOldest => self.shared.queue.reserve_evicting(),
+            RejectionPolicy::Abort | RejectionPolicy::CallerRuns => self.shared.queue.reserve(false),
+        };
+        match slot {
+            Ok(slot) => {
+                // Only read the clock when queue wait times are recorded
+                let enqueued = self.shared.latency.as_ref().map(|_| Instant::now());
+                // The evicted job, if any, is dropped here rather than inside the queue
+                let evicted = slot.push(QueuedJob {
+                    job: wrap(f),
+                    options,
+                    enqueued,
+                });
+                self.shared.submitted.fetch_add(1, Ordering::Relaxed);
+                drop(evicted);
+                Ok(())
+            }
+            Err(Reserve::Closed) => Err(ExecuteError::Shutdown(f)),
+            Err(Reserve::Full) if policy == RejectionPolicy::CallerRuns => {
+                let enqueued = self.shared.latency.as_ref().map(|_| Instant::now());
+                // Run like a worker would, so a panic is recorded rather than unwinding into the caller
+                self.shared.submitted.fetch_add(1, Ordering::Relaxed);
+                self.shared.queue.begin();
+                self.shared.run_job(&self.shared.caller, QueuedJob {
+                    job: wrap(f),
+                    options,
+                    enqueued,
+                });
+                Ok(())
+            }
+            Err(Reserve::Full) => Err(ExecuteError::QueueFull(f)),
+        }
     }
 }
 
 /// Graceful shutdown mechanism
-/// Implement Drop destructor
+/// Implement Drop destructor, equivalent to shutdown followed by waiting for termination
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        drop(self.sender.take());
-
-        for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
+        self.shutdown();
+        self.resume();
 
+        // Workers stuck in a timed out job are detached, they exit once their job returns
+        let mut guard = self.shared.termination_lock.lock().unwrap();
+        while self.shared.live_workers.load(Ordering::SeqCst) > self.shared.hung_workers.load(Ordering::SeqCst) {
+            guard = self.shared.terminated.wait(guard).unwrap();
+        }
+        drop(guard);
+        self.shared.watchdog.stop();
+        let hung = self.shared.watchdog.hung_workers();
+        let workers = mem::take(&mut *self.shared.workers.lock().unwrap());
+        for mut worker in workers {
+            if hung.contains(&worker.state.id) {
+                continue;
+            }
             if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+                // Jobs run under catch_unwind, so a worker thread never ends in a panic
+                let _ = thread.join();
             }
         }
     }
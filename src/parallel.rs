@@ -0,0 +1,173 @@
+use std::ops::Range;
+
+use crate::ThreadPool;
+
+/// Chunks queued per worker by the par_* helpers, more than one so workers that finish early
+/// can pick up the chunks of slower ones
+const CHUNKS_PER_WORKER: usize = 4;
+
+/// Input of the par_* helpers, something that can be cut into chunks processed on different workers,
+/// implemented for slices, `&Vec<T>` and integer ranges
+pub trait ParallelSource: Sync {
+    type Item;
+    type Chunk: IntoIterator<Item = Self::Item>;
+
+    /// Number of items
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Items at the positions in `range`
+    fn chunk(&self, range: Range<usize>) -> Self::Chunk;
+}
+
+impl<'a, T> ParallelSource for &'a [T] where T: Sync, {
+    type Item = &'a T;
+    type Chunk = &'a [T];
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn chunk(&self, range: Range<usize>) -> &'a [T] {
+        &self[range]
+    }
+}
+
+impl<'a, T> ParallelSource for &'a Vec<T> where T: Sync, {
+    type Item = &'a T;
+    type Chunk = &'a [T];
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn chunk(&self, range: Range<usize>) -> &'a [T] {
+        &self[range]
+    }
+}
+
+macro_rules! range_source {
+    ($($t:ty),*) => {
+        $(
+            impl ParallelSource for Range<$t> {
+                type Item = $t;
+                type Chunk = Range<$t>;
+
+                fn len(&self) -> usize {
+                    if self.end > self.start {
+                        // Signed ranges can be wider than the type's MAX
+                        self.end.abs_diff(self.start) as usize
+                    } else {
+                        0
+                    }
+                }
+
+                fn chunk(&self, range: Range<usize>) -> Range<$t> {
+                    // Offsets past MAX wrap around to the right value in two's complement
+                    self.start.wrapping_add(range.start as $t)..self.start.wrapping_add(range.end as $t)
+                }
+            }
+        )*
+    };
+}
+
+range_source!(usize, u32, u64, i32, i64);
+
+impl ThreadPool {
+    /// Call `f` on every item, spread over the workers in chunks
+    ///
+    /// A panic in `f` is resumed once every chunk is done.
+    /// Like `scope`, calling this from one of the pool's own jobs can deadlock
+    pub fn par_for_each<S, F>(&self, source: S, f: F)
+    where
+        S: ParallelSource,
+        F: Fn(S::Item) + Sync,
+    {
+        let chunk_size = self.auto_chunk_size(source.len());
+        self.run_chunks(&source, chunk_size, |chunk| chunk.into_iter().for_each(&f));
+    }
+
+    /// Map every item with `f`, returns the results in input order
+    pub fn par_map<S, U, F>(&self, source: S, f: F) -> Vec<U>
+    where
+        S: ParallelSource,
+        U: Send,
+        F: Fn(S::Item) -> U + Sync,
+    {
+        let chunk_size = self.auto_chunk_size(source.len());
+        let parts = self.run_chunks(&source, chunk_size, |chunk| chunk.into_iter().map(&f).collect::<Vec<U>>());
+        parts.into_iter().flatten().collect()
+    }
+
+    /// Keep the items `predicate` accepts, in input order
+    pub fn par_filter<S, F>(&self, source: S, predicate: F) -> Vec<S::Item>
+    where
+        S: ParallelSource,
+        S::Item: Send,
+        F: Fn(&S::Item) -> bool + Sync,
+    {
+        let chunk_size = self.auto_chunk_size(source.len());
+        let parts = self.run_chunks(&source, chunk_size, |chunk| {
+            chunk.into_iter().filter(|item| predicate(item)).collect::<Vec<S::Item>>()
+        });
+        parts.into_iter().flatten().collect()
+    }
+
+    /// Fold each chunk starting from `identity()` with `fold`, then combine the chunk results
+    /// in input order with `reduce`, an empty source yields `identity()`
+    pub fn par_reduce<S, T, I, F, R>(&self, source: S, identity: I, fold: F, reduce: R) -> T
+    where
+        S: ParallelSource,
+        T: Send,
+        I: Fn() -> T + Sync,
+        F: Fn(T, S::Item) -> T + Sync,
+        R: Fn(T, T) -> T,
+    {
+        let chunk_size = self.auto_chunk_size(source.len());
+        let parts = self.run_chunks(&source, chunk_size, |chunk| chunk.into_iter().fold(identity(), &fold));
+        parts.into_iter().fold(identity(), reduce)
+    }
+
+    /// Call `f` on consecutive chunks of `chunk_size` items, the last one may be shorter,
+    /// returns the results in input order
+    ///
+    /// Panics if `chunk_size` is zero
+    pub fn par_chunks<S, U, F>(&self, source: S, chunk_size: usize, f: F) -> Vec<U>
+    where
+        S: ParallelSource,
+        U: Send,
+        F: Fn(S::Chunk) -> U + Sync,
+    {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        self.run_chunks(&source, chunk_size, f)
+    }
+
+    /// Chunk size giving every worker a few chunks to balance uneven work
+    fn auto_chunk_size(&self, len: usize) -> usize {
+        let chunks = self.num_threads().max(1) * CHUNKS_PER_WORKER;
+        len.div_ceil(chunks).max(1)
+    }
+
+    fn run_chunks<S, U, F>(&self, source: &S, chunk_size: usize, f: F) -> Vec<U>
+    where
+        S: ParallelSource,
+        U: Send,
+        F: Fn(S::Chunk) -> U + Sync,
+    {
+        let len = source.len();
+        let mut results: Vec<Option<U>> = (0..len.div_ceil(chunk_size)).map(|_| None).collect();
+        let f = &f;
+        self.scope(|scope| {
+            for (index, result) in results.iter_mut().enumerate() {
+                let start = index * chunk_size;
+                let range = start..start + chunk_size.min(len - start);
+                scope.spawn(move || *result = Some(f(source.chunk(range))));
+            }
+        });
+        // The scope resumes any panic, so every chunk has produced its result here
+        results.into_iter().map(Option::unwrap).collect()
+    }
+}
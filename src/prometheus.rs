@@ -0,0 +1,186 @@
+//! Prometheus text exposition of a ThreadPool's metrics, enabled by the `prometheus` feature
+
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Histogram, ThreadPool};
+
+/// Upper bounds, in seconds, of the buckets exported for latency histograms
+const BUCKETS: [f64; 13] = [
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// Render every counter, gauge and latency histogram of `pool` in the Prometheus text format,
+/// each sample is labelled with `pool="<name>"`
+///
+/// Worker busy time and latency histograms are only exported when enabled on the pool,
+/// histograms should use HistogramMode::Cumulative since Prometheus expects buckets never to decrease
+pub fn render(pool: &ThreadPool, name: &str) -> String {
+    let stats = pool.stats();
+    let pool_label = format!("pool=\"{}\"", escape(name));
+    let mut out = String::new();
+
+    gauge(&mut out, "threadpool_queued_jobs", "Jobs waiting in the queue", &pool_label, stats.queued as f64);
+    gauge(&mut out, "threadpool_active_workers", "Workers running a job", &pool_label, stats.active_workers as f64);
+    gauge(&mut out, "threadpool_idle_workers", "Live workers waiting for a job", &pool_label, stats.idle_workers as f64);
+    counter(&mut out, "threadpool_jobs_submitted_total", "Jobs accepted into the queue", &pool_label, stats.submitted as f64);
+    counter(&mut out, "threadpool_jobs_completed_total", "Jobs that finished running", &pool_label, stats.completed as f64);
+    counter(&mut out, "threadpool_jobs_panicked_total", "Jobs that panicked", &pool_label, stats.panicked as f64);
+
+    // Busy time is only tracked by pools built with ThreadPoolBuilder::busy_time
+    if stats.workers.iter().any(|worker| worker.busy_time.is_some()) {
+        header(&mut out, "threadpool_worker_busy_seconds_total", "Time each worker spent running jobs", "counter");
+        for worker in &stats.workers {
+            let labels = format!("{},worker=\"{}\"", pool_label, worker.id);
+            let busy_time = worker.busy_time.unwrap_or_default();
+            sample(&mut out, "threadpool_worker_busy_seconds_total", &labels, busy_time.as_secs_f64());
+        }
+    }
+    header(&mut out, "threadpool_worker_jobs_completed_total", "Jobs each worker finished running", "counter");
+    for worker in &stats.workers {
+        let labels = format!("{},worker=\"{}\"", pool_label, worker.id);
+        sample(&mut out, "threadpool_worker_jobs_completed_total", &labels, worker.completed as f64);
+    }
+
+    if let Some(latency) = pool.latency() {
+        histogram(&mut out, "threadpool_queue_wait_seconds", "Time jobs spent queued", &pool_label, &latency.queue_wait);
+        histogram(&mut out, "threadpool_run_time_seconds", "Time jobs spent running", &pool_label, &latency.run_time);
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: f64) {
+    let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, labels: &str, value: f64) {
+    header(out, name, help, "gauge");
+    sample(out, name, labels, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, labels: &str, value: f64) {
+    header(out, name, help, "counter");
+    sample(out, name, labels, value);
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, labels: &str, histogram: &Histogram) {
+    header(out, name, help, "histogram");
+    let bucket = format!("{}_bucket", name);
+    for bound in BUCKETS {
+        let count = histogram.count_at_or_below(Duration::from_secs_f64(bound));
+        sample(out, &bucket, &format!("{},le=\"{}\"", labels, bound), count as f64);
+    }
+    sample(out, &bucket, &format!("{},le=\"+Inf\"", labels), histogram.count() as f64);
+    sample(out, &format!("{}_sum", name), labels, histogram.sum().as_secs_f64());
+    sample(out, &format!("{}_count", name), labels, histogram.count() as f64);
+}
+
+/// Escape a label value as required by the exposition format
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Tiny HTTP endpoint serving `render` on `GET /metrics`,
+/// stops when dropped
+#[derive(Debug)]
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Serve the metrics of `pool` on `addr`, e.g. "127.0.0.1:9100",
+/// requests are answered one at a time on a background thread
+pub fn serve<A>(pool: Arc<ThreadPool>, name: &str, addr: A) -> io::Result<MetricsServer>
+where
+    A: ToSocketAddrs,
+{
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = Arc::clone(&stop);
+    let name = String::from(name);
+    let thread = thread::Builder::new()
+        .name(String::from("threadpool-metrics"))
+        .spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    // A misbehaving client only costs its own request
+                    let _ = respond(stream, &pool, &name);
+                }
+            }
+        })?;
+    Ok(MetricsServer {
+        local_addr,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn respond(mut stream: TcpStream, pool: &ThreadPool, name: &str) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    let request_line = request.split(|&b| b == b'\r').next().unwrap_or(&[]);
+    let mut parts = request_line.split(|&b| b == b' ');
+    let (method, path) = (parts.next(), parts.next());
+    let (status, content_type, body) = match (method, path) {
+        (Some(b"GET"), Some(b"/metrics")) => ("200 OK", "text/plain; version=0.0.4", render(pool, name)),
+        _ => ("404 Not Found", "text/plain", String::from("not found\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+impl MetricsServer {
+    /// Address the server is listening on, useful when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the thread sees the stop flag,
+        // a listener on the unspecified address is reached over loopback
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(match wake_addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        let _ = TcpStream::connect(wake_addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
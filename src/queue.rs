@@ -0,0 +1,469 @@
+use std::hint;
+use std::sync::atomic::{self, AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_deque::{Injector, Steal};
+
+use crate::watchdog::TaskControl;
+use crate::Job;
+
+/// Rounds of busy spinning before an idle worker starts yielding
+const SPIN_ROUNDS: u32 = 6;
+
+/// Rounds of yielding before an idle worker parks on the condvar
+const YIELD_ROUNDS: u32 = 8;
+
+/// Number of priority levels, one injector each
+const LEVELS: usize = 3;
+
+/// Priority of a queued job, `ThreadPool::execute` queues at Normal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Index of the level serving this priority, highest priority first
+    fn level(self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+/// What a pool with a bounded queue does with a job submitted while the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RejectionPolicy {
+    /// Block the caller until there is room in the queue
+    Block,
+    /// Reject the job with ExecuteError::QueueFull
+    #[default]
+    Abort,
+    /// Run the job on the calling thread, it is reported with worker id CALLER_RUNS_WORKER
+    /// and a panic is handled like one on a worker
+    CallerRuns,
+    /// Drop the oldest queued job to make room for the new one
+    DiscardOldest,
+}
+
+/// Reason a slot in the queue could not be reserved
+pub(crate) enum Reserve {
+    Closed,
+    Full,
+}
+
+/// How a job is queued and watched, the defaults are what `ThreadPool::execute` uses
+#[derive(Default)]
+pub(crate) struct JobOptions {
+    pub(crate) priority: Priority,
+    /// Overrides the pool's default job timeout
+    pub(crate) timeout: Option<Duration>,
+    /// Resolves the job's task if it times out
+    pub(crate) control: Option<Arc<dyn TaskControl>>,
+}
+
+/// A job waiting in the queue together with what the worker needs to know about it
+pub(crate) struct QueuedJob {
+    pub(crate) job: Job,
+    pub(crate) options: JobOptions,
+    /// Only set when the pool records queue wait times
+    pub(crate) enqueued: Option<Instant>,
+}
+
+/// Job queue shared by the workers of a pool, optionally bounded
+///
+/// Jobs live in a lock-free segmented MPMC queue, the mutex is only taken to park
+/// idle workers or producers blocked on a full queue, and to wake them up again.
+/// Each parked worker is woken by at most one push, so a burst of jobs does not
+/// pay for a notification per job
+///
+/// Every priority level is FIFO and higher levels are served first. To keep lower
+/// levels from starving, a level that has been passed over `aging` times while it
+/// had jobs waiting gets the next pick, an `aging` of zero serves strictly by priority
+pub(crate) struct JobQueue {
+    levels: [Injector<QueuedJob>; LEVELS],
+    skipped: [AtomicU32; LEVELS],
+    aging: u32,
+    /// Queued jobs plus slots reserved by producers that have not pushed yet
+    len: AtomicUsize,
+    capacity: Option<usize>,
+    closed: AtomicBool,
+    /// Parked workers that have not been handed a wakeup yet
+    parked: Mutex<usize>,
+    parked_hint: AtomicUsize,
+    not_empty: Condvar,
+    not_full: Condvar,
+    blocked_producers: AtomicUsize,
+    /// Jobs taken by a worker that have not finished yet
+    in_flight: AtomicUsize,
+    quiet_lock: Mutex<()>,
+    quiet: Condvar,
+    quiet_waiters: AtomicUsize,
+}
+
+/// Room for one job, released again if dropped without pushing
+pub(crate) struct Slot<'a> {
+    queue: &'a JobQueue,
+    evicted: Option<QueuedJob>,
+    pushed: bool,
+}
+
+impl JobQueue {
+    pub(crate) fn new(capacity: Option<usize>, aging: u32) -> JobQueue {
+        JobQueue {
+            levels: [Injector::new(), Injector::new(), Injector::new()],
+            skipped: [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)],
+            aging,
+            len: AtomicUsize::new(0),
+            capacity,
+            closed: AtomicBool::new(false),
+            parked: Mutex::new(0),
+            parked_hint: AtomicUsize::new(0),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            blocked_producers: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            quiet_lock: Mutex::new(()),
+            quiet: Condvar::new(),
+            quiet_waiters: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reserve room for a job, waiting for a worker to make room if `wait` is set
+    pub(crate) fn reserve(&self, wait: bool) -> Result<Slot<'_>, Reserve> {
+        loop {
+            match self.try_reserve() {
+                Err(Reserve::Full) if wait => self.wait_not_full(),
+                result => return result,
+            }
+        }
+    }
+
+    /// Reserve room for a job, evicting the oldest queued job if the queue is full
+    pub(crate) fn reserve_evicting(&self) -> Result<Slot<'_>, Reserve> {
+        loop {
+            match self.try_reserve() {
+                Err(Reserve::Full) => {
+                    // The evicted job hands its reservation over to the new one,
+                    // if a worker got to it first there is room to reserve again
+                    if let Some(job) = self.steal_oldest_lowest() {
+                        return Ok(Slot {
+                            queue: self,
+                            evicted: Some(job),
+                            pushed: false,
+                        });
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn try_reserve(&self) -> Result<Slot<'_>, Reserve> {
+        match self.capacity {
+            None => {
+                self.len.fetch_add(1, Ordering::SeqCst);
+            }
+            Some(capacity) => {
+                let mut len = self.len.load(Ordering::SeqCst);
+                loop {
+                    if len >= capacity {
+                        return Err(if self.is_closed() { Reserve::Closed } else { Reserve::Full });
+                    }
+                    match self.len.compare_exchange_weak(len, len + 1, Ordering::SeqCst, Ordering::SeqCst) {
+                        Ok(_) => break,
+                        Err(current) => len = current,
+                    }
+                }
+            }
+        }
+        // Checked after counting the slot, so a worker that sees the queue closed
+        // and empty cannot exit while this job is about to be pushed
+        if self.is_closed() {
+            self.release();
+            return Err(Reserve::Closed);
+        }
+        Ok(Slot {
+            queue: self,
+            evicted: None,
+            pushed: false,
+        })
+    }
+
+    /// Take the next job, spinning briefly and then parking while the queue is empty,
+    /// returns None once the queue is closed and drained or as soon as `interrupt` returns true
+    ///
+    /// The job counts as in flight until the worker calls `finish`
+    pub(crate) fn pop<I>(&self, interrupt: I) -> Option<QueuedJob> where I: Fn() -> bool, {
+        let mut round = 0;
+        loop {
+            if interrupt() {
+                return None;
+            }
+            if !self.is_empty() {
+                // Counted before checking `interrupt` again, so whoever sets the interrupt
+                // either sees this worker in flight or is seen by it
+                self.in_flight.fetch_add(1, Ordering::SeqCst);
+                if interrupt() {
+                    self.finish();
+                    return None;
+                }
+                if let Some(job) = self.steal() {
+                    self.release();
+                    return Some(job);
+                }
+                self.finish();
+            }
+            if self.is_drained() {
+                return None;
+            }
+            if round < SPIN_ROUNDS {
+                for _ in 0..1 << round {
+                    hint::spin_loop();
+                }
+            } else if round < SPIN_ROUNDS + YIELD_ROUNDS {
+                thread::yield_now();
+            } else {
+                self.park(&interrupt);
+                round = 0;
+                continue;
+            }
+            round += 1;
+        }
+    }
+
+    /// Take a queued job without waiting, unless `interrupt` returns true,
+    /// the job counts as in flight like one taken with `pop`
+    pub(crate) fn try_pop<I>(&self, interrupt: I) -> Option<QueuedJob> where I: Fn() -> bool, {
+        if self.is_empty() {
+            return None;
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if !interrupt() {
+            if let Some(job) = self.steal() {
+                self.release();
+                return Some(job);
+            }
+        }
+        self.finish();
+        None
+    }
+
+    /// Stop accepting jobs, workers still drain what is already queued
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let mut parked = self.parked.lock().unwrap();
+        *parked = 0;
+        self.parked_hint.store(0, Ordering::SeqCst);
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    /// Count a job run without going through the queue as in flight until `finish`
+    pub(crate) fn begin(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Mark a job taken with `pop` or counted with `begin` as finished
+    pub(crate) fn finish(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.notify_quiet();
+        }
+    }
+
+    /// Block until no job is in flight, and with `empty` set none is queued either,
+    /// or until `deadline` passes, returns true if the queue went quiet
+    pub(crate) fn wait_quiet(&self, empty: bool, deadline: Option<Instant>) -> bool {
+        let mut guard = self.quiet_lock.lock().unwrap();
+        self.quiet_waiters.fetch_add(1, Ordering::SeqCst);
+        let quiet = loop {
+            // A job is counted in flight before it leaves the queue, so it is never missed in between
+            if (!empty || self.len.load(Ordering::SeqCst) == 0) && self.in_flight.load(Ordering::SeqCst) == 0 {
+                break true;
+            }
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break false;
+                    }
+                    guard = self.quiet.wait_timeout(guard, deadline - now).unwrap().0;
+                }
+                None => guard = self.quiet.wait(guard).unwrap(),
+            }
+        };
+        self.quiet_waiters.fetch_sub(1, Ordering::SeqCst);
+        quiet
+    }
+
+    fn notify_quiet(&self) {
+        if self.quiet_waiters.load(Ordering::SeqCst) > 0 {
+            let _guard = self.quiet_lock.lock().unwrap();
+            self.quiet.notify_all();
+        }
+    }
+
+    /// Number of queued jobs, including ones whose producer is about to push them
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Whether the queue has a capacity, only then does the rejection policy come into play
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.capacity.is_some()
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Check whether the queue is closed and every job has been taken
+    pub(crate) fn is_drained(&self) -> bool {
+        self.is_closed() && self.len.load(Ordering::SeqCst) == 0
+    }
+
+    /// Take every queued job, waking producers blocked on a full queue
+    pub(crate) fn drain(&self) -> Vec<QueuedJob> {
+        let mut jobs = Vec::new();
+        while let Some(job) = self.steal() {
+            self.release();
+            jobs.push(job);
+        }
+        jobs
+    }
+
+    /// Take the next job by priority, serving starved levels first
+    fn steal(&self) -> Option<QueuedJob> {
+        for level in (1..LEVELS).rev() {
+            if self.aging > 0 && self.skipped[level].load(Ordering::Relaxed) >= self.aging {
+                if let Some(job) = self.steal_from(level) {
+                    self.skipped[level].store(0, Ordering::Relaxed);
+                    return Some(job);
+                }
+            }
+        }
+        for level in 0..LEVELS {
+            if let Some(job) = self.steal_from(level) {
+                for lower in level + 1..LEVELS {
+                    if !self.levels[lower].is_empty() {
+                        self.skipped[lower].fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                self.skipped[level].store(0, Ordering::Relaxed);
+                return Some(job);
+            }
+        }
+        None
+    }
+
+    /// Take the oldest job of the lowest non-empty priority
+    fn steal_oldest_lowest(&self) -> Option<QueuedJob> {
+        (0..LEVELS).rev().find_map(|level| self.steal_from(level))
+    }
+
+    fn steal_from(&self, level: usize) -> Option<QueuedJob> {
+        loop {
+            match self.levels[level].steal() {
+                Steal::Success(job) => return Some(job),
+                Steal::Empty => return None,
+                Steal::Retry => hint::spin_loop(),
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.levels.iter().all(Injector::is_empty)
+    }
+
+    /// Give back a slot, waking a producer blocked on a full queue,
+    /// or every parked worker once a closed queue is drained
+    fn release(&self) {
+        let empty = self.len.fetch_sub(1, Ordering::SeqCst) == 1;
+        if empty {
+            self.notify_quiet();
+        }
+        if empty && self.is_closed() {
+            let _guard = self.parked.lock().unwrap();
+            self.not_empty.notify_all();
+        } else if self.capacity.is_some() && self.blocked_producers.load(Ordering::SeqCst) > 0 {
+            let _guard = self.parked.lock().unwrap();
+            self.not_full.notify_one();
+        }
+    }
+
+    /// Wake every parked worker, e.g. so they can check whether they should retire
+    pub(crate) fn unpark_all(&self) {
+        let mut parked = self.parked.lock().unwrap();
+        *parked = 0;
+        self.parked_hint.store(0, Ordering::SeqCst);
+        self.not_empty.notify_all();
+    }
+
+    /// Park until a push hands this worker a wakeup, a spurious wakeup only leaves
+    /// the parked count too high, which costs at most one extra notification
+    fn park(&self, interrupt: &dyn Fn() -> bool) {
+        let mut parked = self.parked.lock().unwrap();
+        *parked += 1;
+        self.parked_hint.store(*parked, Ordering::SeqCst);
+        // Pairs with the fence in Slot::push so a concurrent push is either
+        // seen here or sees this worker parked
+        atomic::fence(Ordering::SeqCst);
+        // Checked under the lock so an interrupt raised before unpark_all is not missed
+        if self.is_empty() && !interrupt() && !self.is_drained() {
+            drop(self.not_empty.wait(parked).unwrap());
+        } else {
+            *parked -= 1;
+            self.parked_hint.store(*parked, Ordering::SeqCst);
+        }
+    }
+
+    /// Hand a wakeup to one parked worker, if there is one
+    fn unpark_one(&self) {
+        let mut parked = self.parked.lock().unwrap();
+        if *parked > 0 {
+            *parked -= 1;
+            self.parked_hint.store(*parked, Ordering::SeqCst);
+            self.not_empty.notify_one();
+        }
+    }
+
+    fn wait_not_full(&self) {
+        let guard = self.parked.lock().unwrap();
+        self.blocked_producers.fetch_add(1, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+        let full = self.capacity.is_some_and(|capacity| self.len.load(Ordering::SeqCst) >= capacity);
+        if full && !self.is_closed() {
+            drop(self.not_full.wait(guard).unwrap());
+        }
+        self.blocked_producers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Slot<'_> {
+    /// Queue the job, returns the evicted job so the caller decides where it is dropped
+    pub(crate) fn push(mut self, job: QueuedJob) -> Option<QueuedJob> {
+        let queue = self.queue;
+        queue.levels[job.options.priority.level()].push(job);
+        self.pushed = true;
+        atomic::fence(Ordering::SeqCst);
+        if queue.parked_hint.load(Ordering::SeqCst) > 0 {
+            queue.unpark_one();
+        }
+        self.evicted.take()
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        if !self.pushed {
+            self.queue.release();
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{PoolCreationError, RejectionPolicy, ThreadPool};
+
+/// Thread pool that runs jobs after a delay or periodically,
+/// a timer thread keeps the pending jobs in a heap and queues each one on the pool when it is due
+///
+/// Jobs still waiting for their time when the pool is dropped are discarded
+pub struct ScheduledThreadPool {
+    pool: Arc<ThreadPool>,
+    timer: Arc<Timer>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Handle to a scheduled job, used to cancel it
+#[derive(Debug, Clone)]
+pub struct ScheduledHandle {
+    state: Arc<ScheduleState>,
+    timer: Weak<Timer>,
+}
+
+#[derive(Debug)]
+struct ScheduleState {
+    cancelled: AtomicBool,
+    done: AtomicBool,
+}
+
+/// Work done on the timer thread once an entry is due, usually queueing a job on the pool
+type Fire = Box<dyn FnOnce(&ThreadPool) + Send>;
+
+/// Pending entries shared between the scheduling threads and the timer thread
+struct Timer {
+    state: Mutex<TimerState>,
+    wake: Condvar,
+}
+
+struct TimerState {
+    entries: BinaryHeap<Entry>,
+    next_seq: u64,
+    shutdown: bool,
+}
+
+/// Entry of the timer heap, entries due at the same instant fire in scheduling order
+struct Entry {
+    at: Instant,
+    seq: u64,
+    state: Arc<ScheduleState>,
+    fire: Fire,
+}
+
+/// How a periodic job picks the time of its next run
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    /// A fixed time after the previous run was due
+    Rate(Duration),
+    /// A fixed time after the previous run finished
+    Delay(Duration),
+}
+
+/// Periodic job, rescheduled by the worker that ran it so runs never overlap
+struct PeriodicJob {
+    f: Mutex<Box<dyn FnMut() + Send>>,
+    period: Period,
+    state: Arc<ScheduleState>,
+}
+
+impl ScheduledThreadPool {
+    /// Create a pool with `size` workers and a timer thread
+    pub fn new(size: usize) -> Result<ScheduledThreadPool, PoolCreationError> {
+        ScheduledThreadPool::with_pool(ThreadPool::new(size)?)
+    }
+
+    /// Schedule jobs onto an already configured pool
+    ///
+    /// Due jobs are queued from the timer thread, so a pool with a bounded queue is refused if its
+    /// rejection policy would block that thread or run the job on it, i.e. RejectionPolicy::Block
+    /// or RejectionPolicy::CallerRuns
+    pub fn with_pool(pool: ThreadPool) -> Result<ScheduledThreadPool, PoolCreationError> {
+        let policy = pool.shared.rejection_policy;
+        if pool.shared.queue.is_bounded() && matches!(policy, RejectionPolicy::Block | RejectionPolicy::CallerRuns) {
+            return Err(PoolCreationError::new(format!("Scheduled pools cannot use {:?} with a bounded queue", policy)));
+        }
+        let pool = Arc::new(pool);
+        let timer = Arc::new(Timer {
+            state: Mutex::new(TimerState {
+                entries: BinaryHeap::new(),
+                next_seq: 0,
+                shutdown: false,
+            }),
+            wake: Condvar::new(),
+        });
+        let thread = {
+            let pool = Arc::clone(&pool);
+            let timer = Arc::clone(&timer);
+            thread::Builder::new()
+                .name(String::from("scheduled-timer"))
+                .spawn(move || timer.run(&pool))
+                .map_err(|e| PoolCreationError::new(format!("Failed to spawn timer thread: {}", e)))?
+        };
+        Ok(ScheduledThreadPool {
+            pool,
+            timer,
+            thread: Some(thread),
+        })
+    }
+
+    /// The pool the scheduled jobs run on, also usable for immediate jobs
+    pub fn pool(&self) -> &ThreadPool {
+        &self.pool
+    }
+
+    /// Run `f` once after `delay`
+    pub fn schedule<F>(&self, delay: Duration, f: F) -> ScheduledHandle where F: FnOnce() + Send + 'static, {
+        let state = ScheduleState::new();
+        let job_state = Arc::clone(&state);
+        self.timer.add(
+            Instant::now() + delay,
+            Arc::clone(&state),
+            Box::new(move |pool: &ThreadPool| {
+                if job_state.is_cancelled() {
+                    return;
+                }
+                let run_state = Arc::clone(&job_state);
+                let queued = pool.execute(move || {
+                    // Cancelled while waiting in the pool's queue
+                    if !run_state.is_cancelled() {
+                        run_state.done.store(true, Ordering::SeqCst);
+                        f();
+                    }
+                });
+                if queued.is_err() {
+                    job_state.done.store(true, Ordering::SeqCst);
+                }
+            }),
+        );
+        self.handle(state)
+    }
+
+    /// Run `f` after `initial` and then every `period`, measured between the times runs are due,
+    /// a run that is late starts as soon as the previous one finishes and runs never overlap
+    ///
+    /// The job stops repeating once cancelled or after it panics
+    pub fn schedule_at_fixed_rate<F>(&self, initial: Duration, period: Duration, f: F) -> ScheduledHandle
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.schedule_periodic(initial, Period::Rate(period), f)
+    }
+
+    /// Run `f` after `initial` and then `delay` after each run finishes
+    ///
+    /// The job stops repeating once cancelled or after it panics
+    pub fn schedule_with_fixed_delay<F>(&self, initial: Duration, delay: Duration, f: F) -> ScheduledHandle
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.schedule_periodic(initial, Period::Delay(delay), f)
+    }
+
+    fn schedule_periodic<F>(&self, initial: Duration, period: Period, f: F) -> ScheduledHandle
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let state = ScheduleState::new();
+        let job = Arc::new(PeriodicJob {
+            f: Mutex::new(Box::new(f)),
+            period,
+            state: Arc::clone(&state),
+        });
+        let due = Instant::now() + initial;
+        self.timer.add(due, Arc::clone(&state), PeriodicJob::fire(job, Arc::clone(&self.timer), due));
+        self.handle(state)
+    }
+
+    fn handle(&self, state: Arc<ScheduleState>) -> ScheduledHandle {
+        ScheduledHandle {
+            state,
+            timer: Arc::downgrade(&self.timer),
+        }
+    }
+}
+
+impl ScheduledHandle {
+    /// Stop the job from running again, a run already in progress is left to finish
+    ///
+    /// The pending timer entry is removed right away, so the job's closure is dropped
+    /// unless it is running or already queued on the pool
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.done.store(true, Ordering::SeqCst);
+        if let Some(timer) = self.timer.upgrade() {
+            timer.remove_cancelled();
+        }
+    }
+
+    /// Check whether the job has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.state.is_cancelled()
+    }
+
+    /// Check whether the job will not run again,
+    /// a cancelled job, a one-shot job that started or a periodic job that panicked or lost its pool
+    pub fn is_done(&self) -> bool {
+        self.state.done.load(Ordering::SeqCst)
+    }
+}
+
+impl ScheduleState {
+    fn new() -> Arc<ScheduleState> {
+        Arc::new(ScheduleState {
+            cancelled: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+        })
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl PeriodicJob {
+    /// Timer entry queueing the run due at `due`
+    fn fire(job: Arc<PeriodicJob>, timer: Arc<Timer>, due: Instant) -> Fire {
+        Box::new(move |pool: &ThreadPool| {
+            if job.state.is_cancelled() {
+                return;
+            }
+            let state = Arc::clone(&job.state);
+            if pool.execute(move || job.run(&timer, due)).is_err() {
+                state.done.store(true, Ordering::SeqCst);
+            }
+        })
+    }
+
+    fn run(self: Arc<PeriodicJob>, timer: &Arc<Timer>, due: Instant) {
+        if self.state.is_cancelled() {
+            return;
+        }
+        let result = {
+            let mut f = self.f.lock().unwrap();
+            panic::catch_unwind(AssertUnwindSafe(|| (*f)()))
+        };
+        if let Err(payload) = result {
+            // Resumed so the worker reports the panic like any other job
+            self.state.done.store(true, Ordering::SeqCst);
+            panic::resume_unwind(payload);
+        }
+        let next = match self.period {
+            Period::Rate(period) => due + period,
+            Period::Delay(delay) => Instant::now() + delay,
+        };
+        if !self.state.is_cancelled() {
+            let state = Arc::clone(&self.state);
+            timer.add(next, state, PeriodicJob::fire(self, Arc::clone(timer), next));
+        }
+    }
+}
+
+impl Timer {
+    fn add(&self, at: Instant, schedule: Arc<ScheduleState>, fire: Fire) {
+        let mut state = self.state.lock().unwrap();
+        // Checked under the lock, so an entry added after a cancel cleaned up is not left behind
+        if state.shutdown || schedule.is_cancelled() {
+            return;
+        }
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.entries.push(Entry {
+            at,
+            seq,
+            state: schedule,
+            fire,
+        });
+        drop(state);
+        self.wake.notify_one();
+    }
+
+    /// Drop the entries of cancelled jobs instead of keeping them until they are due
+    fn remove_cancelled(&self) {
+        let cancelled: Vec<Entry> = {
+            let mut state = self.state.lock().unwrap();
+            let (cancelled, pending): (Vec<Entry>, Vec<Entry>) =
+                std::mem::take(&mut state.entries).into_iter().partition(|entry| entry.state.is_cancelled());
+            state.entries = BinaryHeap::from(pending);
+            cancelled
+        };
+        // Dropped without the lock held, the closures may own handles or jobs that schedule again
+        drop(cancelled);
+    }
+
+    /// Fire entries as they come due until the timer is shut down
+    fn run(&self, pool: &ThreadPool) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.shutdown {
+                break;
+            }
+            let now = Instant::now();
+            match state.entries.peek().map(|entry| entry.at) {
+                Some(at) if at <= now => {
+                    let entry = state.entries.pop().unwrap();
+                    drop(state);
+                    (entry.fire)(pool);
+                    state = self.state.lock().unwrap();
+                }
+                Some(at) => state = self.wake.wait_timeout(state, at - now).unwrap().0,
+                None => state = self.wake.wait(state).unwrap(),
+            }
+        }
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.at == other.at && self.seq == other.seq
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Reversed so the max-heap yields the earliest entry first
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> CmpOrdering {
+        other.at.cmp(&self.at).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl Debug for ScheduledThreadPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScheduledThreadPool")
+            .field("pool", &self.pool)
+            .field("pending", &self.timer.state.lock().unwrap().entries.len())
+            .finish()
+    }
+}
+
+/// Graceful shutdown mechanism,
+/// pending entries are discarded and the pool drains the jobs already queued on it
+impl Drop for ScheduledThreadPool {
+    fn drop(&mut self) {
+        // Taken out of the lock so entries holding the timer are dropped without it held
+        let entries = {
+            let mut state = self.timer.state.lock().unwrap();
+            state.shutdown = true;
+            std::mem::take(&mut state.entries)
+        };
+        self.timer.wake.notify_all();
+        drop(entries);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
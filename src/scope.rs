@@ -0,0 +1,119 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::queue::JobOptions;
+use crate::{Job, ThreadPool};
+
+/// Scope for jobs that may borrow from the stack of the caller of `ThreadPool::scope`
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'env ThreadPool,
+    state: Arc<ScopeState>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+/// Bookkeeping shared between a scope and the jobs spawned in it
+struct ScopeState {
+    pending: Mutex<usize>,
+    done: Condvar,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Job spawned in a scope, completes its scope when run or dropped
+struct ScopedJob<F> {
+    f: Option<F>,
+    state: Arc<ScopeState>,
+}
+
+impl ThreadPool {
+    /// Run `f` with a Scope whose spawned jobs may borrow non-'static data,
+    /// every spawned job has finished by the time this returns
+    ///
+    /// If `f` or any spawned job panics, the first panic is resumed once all jobs are done.
+    /// Calling this from one of the pool's own jobs can deadlock if every worker ends up waiting
+    pub fn scope<'env, F, T>(&'env self, f: F) -> T
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                done: Condvar::new(),
+                panic: Mutex::new(None),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.state.wait();
+        if let Some(payload) = scope.state.panic.lock().unwrap().take() {
+            panic::resume_unwind(payload);
+        }
+        match result {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Run `f` on the pool, if the pool rejects it the job runs on the calling thread instead
+    pub fn spawn<F>(&'scope self, f: F) where F: FnOnce() + Send + 'scope, {
+        *self.state.pending.lock().unwrap() += 1;
+        let scoped = ScopedJob {
+            f: Some(f),
+            state: Arc::clone(&self.state),
+        };
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || scoped.run());
+        // SAFETY: `ThreadPool::scope` does not return before every ScopedJob has been run
+        // or dropped, so the borrows captured by `f` outlive the job
+        let job: Job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        if let Err(rejected) = self.pool.dispatch(job, JobOptions::default(), |job| job) {
+            rejected.into_inner()();
+        }
+    }
+}
+
+impl ScopeState {
+    fn wait(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        while *pending > 0 {
+            pending = self.done.wait(pending).unwrap();
+        }
+    }
+
+    fn record_panic(&self, payload: Box<dyn Any + Send>) {
+        let mut panic = self.panic.lock().unwrap();
+        if panic.is_none() {
+            *panic = Some(payload);
+        }
+    }
+}
+
+impl<F> ScopedJob<F> where F: FnOnce(), {
+    fn run(mut self) {
+        let f = self.f.take().unwrap();
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+            self.state.record_panic(payload);
+        }
+    }
+}
+
+impl<F> Drop for ScopedJob<F> {
+    fn drop(&mut self) {
+        // A job dropped without running, e.g. evicted from a full queue, still fails the scope
+        if let Some(f) = self.f.take() {
+            drop(f);
+            self.state.record_panic(Box::new("scoped job was discarded before it ran"));
+        }
+        let mut pending = self.state.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            self.state.done.notify_all();
+        }
+    }
+}
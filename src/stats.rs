@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Point in time snapshot of a pool's counters, see `ThreadPool::stats`
+///
+/// Counters are read one by one while the pool keeps running, so they are not a consistent cut.
+/// Completed jobs are summed from per-worker counters when the snapshot is taken, keeping
+/// shared counters off the path every job takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Jobs waiting in the queue
+    pub queued: usize,
+    /// Workers currently running a job
+    pub active_workers: usize,
+    /// Live workers waiting for a job
+    pub idle_workers: usize,
+    /// Jobs accepted since the pool was created, including jobs run on the submitting thread
+    /// under RejectionPolicy::CallerRuns
+    pub submitted: u64,
+    /// Jobs that finished running, including the ones that panicked
+    pub completed: u64,
+    /// Jobs that panicked
+    pub panicked: u64,
+    /// Per worker counters of the workers that have not exited, in worker id order
+    pub workers: Vec<WorkerStats>,
+}
+
+/// Counters of a single worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub id: usize,
+    /// Whether the worker is running a job right now
+    pub busy: bool,
+    /// Jobs this worker finished running
+    pub completed: u64,
+    /// Time this worker spent running jobs,
+    /// None unless enabled with `ThreadPoolBuilder::busy_time`
+    pub busy_time: Option<Duration>,
+}
+
+/// Counters owned by a worker thread and read by `ThreadPool::stats`
+#[derive(Debug)]
+pub(crate) struct WorkerState {
+    pub(crate) id: usize,
+    /// Jobs running on this worker, more than one while it helps out in `ThreadPool::join`
+    running: AtomicUsize,
+    completed: AtomicU64,
+    /// None unless the pool times every job
+    busy_nanos: Option<AtomicU64>,
+}
+
+impl WorkerState {
+    pub(crate) fn new(id: usize, busy_time: bool) -> WorkerState {
+        WorkerState {
+            id,
+            running: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            busy_nanos: busy_time.then(|| AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn job_started(&self) {
+        self.running.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a finished job, `duration` is None if the job was not timed
+    pub(crate) fn job_finished(&self, duration: Option<Duration>) {
+        if let (Some(busy_nanos), Some(duration)) = (self.busy_nanos.as_ref(), duration) {
+            busy_nanos.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+        }
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        self.running.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_busy(&self) -> bool {
+        self.running.load(Ordering::Relaxed) > 0
+    }
+
+    pub(crate) fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn snapshot(&self) -> WorkerStats {
+        WorkerStats {
+            id: self.id,
+            busy: self.is_busy(),
+            completed: self.completed.load(Ordering::Relaxed),
+            busy_time: self.busy_nanos.as_ref().map(|busy_nanos| Duration::from_nanos(busy_nanos.load(Ordering::Relaxed))),
+        }
+    }
+}
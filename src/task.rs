@@ -0,0 +1,269 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::cancel::OnCancel;
+use crate::watchdog::TaskControl;
+use crate::CancellationToken;
+
+/// Error reported by a TaskHandle when the job did not produce a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    /// The job panicked, carries the panic message
+    Panicked(String),
+    /// The job was dropped without running, e.g. evicted from a full queue
+    Discarded,
+    /// The task's CancellationToken was cancelled before the job finished, it may still be running
+    Cancelled,
+    /// The job overran its timeout, it may still be running
+    TimedOut,
+}
+
+impl Display for TaskError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskError::Panicked(message) => write!(f, "task panicked: {}", message),
+            TaskError::Discarded => write!(f, "task was discarded before it ran"),
+            TaskError::Cancelled => write!(f, "task was cancelled"),
+            TaskError::TimedOut => write!(f, "task timed out"),
+        }
+    }
+}
+
+impl Error for TaskError {}
+
+/// Handle to the result of a job submitted with `ThreadPool::submit`,
+/// can be joined from a blocking thread or awaited from async code
+pub struct TaskHandle<T> {
+    packet: Arc<Packet<T>>,
+}
+
+/// Sending half of a task, owned by the job running on the pool,
+/// dropping it unused completes the task with TaskError::Discarded
+pub(crate) struct Completer<T> {
+    packet: Arc<Packet<T>>,
+}
+
+/// State shared between a TaskHandle and its Completer
+struct Packet<T> {
+    state: Mutex<State<T>>,
+    done: Condvar,
+}
+
+struct State<T> {
+    result: Option<Result<T, TaskError>>,
+    finished: bool,
+    waker: Option<Waker>,
+}
+
+/// Lets the watchdog resolve a task while its job is still running
+struct TimeoutControl<T> {
+    packet: Arc<Packet<T>>,
+    token: Option<CancellationToken>,
+}
+
+/// Create a connected Completer and TaskHandle pair
+pub(crate) fn task<T>() -> (Completer<T>, TaskHandle<T>) {
+    let packet = Arc::new(Packet {
+        state: Mutex::new(State {
+            result: None,
+            finished: false,
+            waker: None,
+        }),
+        done: Condvar::new(),
+    });
+    let completer = Completer {
+        packet: Arc::clone(&packet),
+    };
+    (completer, TaskHandle { packet })
+}
+
+/// Extract a readable message from a panic payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Box<dyn Any>")
+    }
+}
+
+impl<T> Completer<T> {
+    /// Run the job and publish its result, a panic is reported as TaskError::Panicked
+    /// and then resumed so the worker still accounts for it
+    pub(crate) fn run<F>(self, f: F) where F: FnOnce() -> T, {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(value) => self.complete(Ok(value)),
+            Err(payload) => {
+                self.complete(Err(TaskError::Panicked(panic_message(payload.as_ref()))));
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    /// Run the job unless `token` is already cancelled, a job that returns after
+    /// its token was cancelled is reported as TaskError::Cancelled
+    pub(crate) fn run_cancellable<F>(self, token: CancellationToken, f: F) where F: FnOnce(&CancellationToken) -> T, {
+        if token.is_cancelled() {
+            self.complete(Err(TaskError::Cancelled));
+            return;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| f(&token))) {
+            Ok(_) if token.is_cancelled() => self.complete(Err(TaskError::Cancelled)),
+            Ok(value) => self.complete(Ok(value)),
+            Err(payload) => {
+                self.complete(Err(TaskError::Panicked(panic_message(payload.as_ref()))));
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+
+    /// Resolve the task with TaskError::Cancelled as soon as `token` is cancelled,
+    /// rather than once the job is dequeued
+    pub(crate) fn cancel_with(&self, token: &CancellationToken)
+    where
+        T: Send + 'static,
+    {
+        let packet: Weak<Packet<T>> = Arc::downgrade(&self.packet);
+        token.register(packet);
+    }
+
+    /// Handle through which the watchdog times out this task and cancels `token`
+    pub(crate) fn control(&self, token: Option<CancellationToken>) -> Arc<dyn TaskControl>
+    where
+        T: Send + 'static,
+    {
+        Arc::new(TimeoutControl {
+            packet: Arc::clone(&self.packet),
+            token,
+        })
+    }
+
+    fn complete(&self, result: Result<T, TaskError>) {
+        self.packet.complete(result);
+    }
+}
+
+impl<T> Packet<T> {
+    /// Publish the result, a no-op if the task already has one
+    fn complete(&self, result: Result<T, TaskError>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            if state.finished {
+                return;
+            }
+            state.result = Some(result);
+            state.finished = true;
+            state.waker.take()
+        };
+        self.done.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> TaskControl for TimeoutControl<T> where T: Send, {
+    fn time_out(&self) {
+        self.packet.complete(Err(TaskError::TimedOut));
+        if let Some(token) = self.token.as_ref() {
+            token.cancel();
+        }
+    }
+}
+
+impl<T> OnCancel for Packet<T> where T: Send, {
+    fn cancelled(&self) {
+        self.complete(Err(TaskError::Cancelled));
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().finished
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(TaskError::Discarded));
+    }
+}
+
+impl<T> TaskHandle<T> {
+    /// Block until the job finishes and return its result
+    ///
+    /// Panics if the result was already taken by `try_join` or `join_timeout`
+    pub fn join(self) -> Result<T, TaskError> {
+        let mut state = self.packet.state.lock().unwrap();
+        while !state.finished {
+            state = self.packet.done.wait(state).unwrap();
+        }
+        take_result(&mut state)
+    }
+
+    /// Return the result if the job has finished, without blocking
+    ///
+    /// Panics if the result was already taken
+    pub fn try_join(&mut self) -> Option<Result<T, TaskError>> {
+        let mut state = self.packet.state.lock().unwrap();
+        if state.finished {
+            Some(take_result(&mut state))
+        } else {
+            None
+        }
+    }
+
+    /// Block for at most `timeout` waiting for the job to finish,
+    /// returns None if the job is still running
+    ///
+    /// Panics if the result was already taken
+    pub fn join_timeout(&mut self, timeout: Duration) -> Option<Result<T, TaskError>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.packet.state.lock().unwrap();
+        while !state.finished {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            state = self.packet.done.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        Some(take_result(&mut state))
+    }
+
+    /// Check whether the job has finished
+    pub fn is_finished(&self) -> bool {
+        self.packet.state.lock().unwrap().finished
+    }
+}
+
+fn take_result<T>(state: &mut State<T>) -> Result<T, TaskError> {
+    state.result.take().expect("task result already taken")
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Result<T, TaskError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.packet.state.lock().unwrap();
+        if state.finished {
+            Poll::Ready(take_result(&mut state))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Shared, Worker};
+
+/// Callback invoked with the worker id and the elapsed run time of a job that timed out
+pub(crate) type TimeoutHook = dyn Fn(usize, Duration) + Send + Sync;
+
+/// Handle the watchdog uses to resolve a task whose job overran its timeout
+pub(crate) trait TaskControl: Send + Sync {
+    /// Report the task as timed out and signal its cancellation token, if it has one
+    fn time_out(&self);
+}
+
+/// Tracks the deadlines of running jobs and times out the ones that overrun,
+/// the watchdog thread is only started once a timeout is in use
+pub(crate) struct Watchdog {
+    state: Mutex<WatchState>,
+    wake: Condvar,
+    pub(crate) default_timeout: Option<Duration>,
+    replace_workers: bool,
+    on_timeout: Option<Box<TimeoutHook>>,
+}
+
+struct WatchState {
+    /// Running jobs with a deadline, a worker helping out in `ThreadPool::join` can run several
+    running: HashMap<u64, Watched>,
+    next_key: u64,
+    thread: Option<thread::JoinHandle<()>>,
+    stopped: bool,
+}
+
+struct Watched {
+    worker: usize,
+    started: Instant,
+    deadline: Instant,
+    control: Option<Arc<dyn TaskControl>>,
+    timed_out: bool,
+    /// A replacement worker was spawned, so this worker retires once its job returns
+    replaced: bool,
+}
+
+impl Watchdog {
+    pub(crate) fn new(default_timeout: Option<Duration>, replace_workers: bool, on_timeout: Option<Box<TimeoutHook>>) -> Watchdog {
+        Watchdog {
+            state: Mutex::new(WatchState {
+                running: HashMap::new(),
+                next_key: 0,
+                thread: None,
+                stopped: false,
+            }),
+            wake: Condvar::new(),
+            default_timeout,
+            replace_workers,
+            on_timeout,
+        }
+    }
+
+    /// Start the watchdog thread unless it is already running
+    pub(crate) fn start(shared: &Arc<Shared>) {
+        let mut state = shared.watchdog.state.lock().unwrap();
+        if state.thread.is_some() || state.stopped {
+            return;
+        }
+        let watched = Arc::clone(shared);
+        // Without the thread timeouts are not enforced, but jobs still run
+        state.thread = thread::Builder::new()
+            .name(String::from("threadpool-watchdog"))
+            .spawn(move || Watchdog::run(&watched))
+            .ok();
+    }
+
+    /// Stop the watchdog thread and wait for it to exit
+    pub(crate) fn stop(&self) {
+        let thread = {
+            let mut state = self.state.lock().unwrap();
+            state.stopped = true;
+            state.thread.take()
+        };
+        self.wake.notify_all();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+
+    /// Watch the job `worker` just started, returns the key to unwatch it with
+    pub(crate) fn watch(&self, worker: usize, started: Instant, timeout: Duration, control: Option<Arc<dyn TaskControl>>) -> u64 {
+        let watched = Watched {
+            worker,
+            started,
+            deadline: started + timeout,
+            control,
+            timed_out: false,
+            replaced: false,
+        };
+        let mut state = self.state.lock().unwrap();
+        let key = state.next_key;
+        state.next_key += 1;
+        state.running.insert(key, watched);
+        drop(state);
+        self.wake.notify_one();
+        key
+    }
+
+    /// Stop watching a finished job, returns whether it timed out
+    /// and whether its worker has been replaced
+    pub(crate) fn unwatch(&self, key: u64) -> (bool, bool) {
+        match self.state.lock().unwrap().running.remove(&key) {
+            Some(watched) => (watched.timed_out, watched.replaced),
+            None => (false, false),
+        }
+    }
+
+    /// Ids of the workers stuck in a job that timed out
+    pub(crate) fn hung_workers(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap();
+        state.running.values().filter(|watched| watched.timed_out).map(|watched| watched.worker).collect()
+    }
+
+    fn run(shared: &Arc<Shared>) {
+        let watchdog = &shared.watchdog;
+        let mut state = watchdog.state.lock().unwrap();
+        loop {
+            if state.stopped {
+                break;
+            }
+            let now = Instant::now();
+            let replace = watchdog.replace_workers && !shared.shutdown.load(Ordering::SeqCst);
+            let mut expired = Vec::new();
+            for watched in state.running.values_mut() {
+                if !watched.timed_out && watched.deadline <= now {
+                    watched.timed_out = true;
+                    watched.replaced = replace;
+                    // Counted under the lock so the worker cannot uncount it first
+                    shared.hung_workers.fetch_add(1, Ordering::SeqCst);
+                    expired.push((watched.worker, now - watched.started, watched.control.take()));
+                }
+            }
+            if expired.is_empty() {
+                let next = state.running.values().filter(|watched| !watched.timed_out).map(|watched| watched.deadline).min();
+                state = match next {
+                    Some(deadline) => watchdog.wake.wait_timeout(state, deadline - now).unwrap().0,
+                    None => watchdog.wake.wait(state).unwrap(),
+                };
+                continue;
+            }
+            drop(state);
+            shared.notify_terminated();
+            for (worker, elapsed, control) in expired {
+                if let Some(control) = control {
+                    control.time_out();
+                }
+                if let Some(on_timeout) = watchdog.on_timeout.as_ref() {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| on_timeout(worker, elapsed)));
+                }
+                if replace {
+                    Worker::spawn_replacement(shared);
+                }
+            }
+            state = watchdog.state.lock().unwrap();
+        }
+    }
+}
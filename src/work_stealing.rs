@@ -0,0 +1,223 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::iter;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+
+use crate::task;
+use crate::{ExecuteError, Job, PoolCreationError, TaskHandle};
+
+/// Work stealing thread pool,
+/// each worker owns a LIFO deque and idle workers steal FIFO from the others
+/// and from a global injector queue fed by threads outside the pool
+pub struct WorkStealingPool {
+    shared: Arc<StealShared>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+/// State shared between the work stealing pool and its workers
+struct StealShared {
+    injector: Injector<Job>,
+    stealers: Vec<Stealer<Job>>,
+    sleep_lock: Mutex<()>,
+    wake: Condvar,
+    sleepers: AtomicUsize,
+    shutdown: AtomicBool,
+    panic_count: AtomicUsize,
+}
+
+/// The deque of the pool worker running on this thread
+struct LocalQueue {
+    pool: *const StealShared,
+    deque: Worker<Job>,
+}
+
+thread_local! {
+    static LOCAL: RefCell<Option<LocalQueue>> = const { RefCell::new(None) };
+}
+
+impl WorkStealingPool {
+    pub fn new(size: usize) -> Result<WorkStealingPool, PoolCreationError> {
+        if size < 1 {
+            return Err(PoolCreationError::new(String::from("Invalid size")));
+        }
+        let deques: Vec<Worker<Job>> = (0..size).map(|_| Worker::new_lifo()).collect();
+        let shared = Arc::new(StealShared {
+            injector: Injector::new(),
+            stealers: deques.iter().map(Worker::stealer).collect(),
+            sleep_lock: Mutex::new(()),
+            wake: Condvar::new(),
+            sleepers: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+            panic_count: AtomicUsize::new(0),
+        });
+        // Workers spawned before a failure are shut down by dropping the partial pool
+        let mut pool = WorkStealingPool {
+            shared,
+            threads: Vec::with_capacity(size),
+        };
+        for (index, deque) in deques.into_iter().enumerate() {
+            let shared = Arc::clone(&pool.shared);
+            let thread = thread::Builder::new()
+                .spawn(move || shared.run_worker(index, deque))
+                .map_err(|e| PoolCreationError::new(format!("Failed to spawn worker {}: {}", index, e)))?;
+            pool.threads.push(thread);
+        }
+        Ok(pool)
+    }
+
+    /// Queue a job, jobs queued from one of this pool's workers go to that worker's
+    /// own deque, all others go to the global injector
+    pub fn execute<F>(&self, f: F) -> Result<(), ExecuteError<F>> where F: FnOnce() + Send + 'static, {
+        self.dispatch(f, |f| Box::new(f))
+    }
+
+    /// Submit a job whose return value can be collected through the returned TaskHandle
+    pub fn submit<F, T>(&self, f: F) -> Result<TaskHandle<T>, ExecuteError<F>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completer, handle) = task::task();
+        self.dispatch(f, move |f| Box::new(move || completer.run(f)))?;
+        Ok(handle)
+    }
+
+    /// Number of worker threads
+    pub fn num_threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Number of jobs that have panicked since the pool was created
+    pub fn panic_count(&self) -> usize {
+        self.shared.panic_count.load(Ordering::SeqCst)
+    }
+
+    fn dispatch<F, W>(&self, f: F, wrap: W) -> Result<(), ExecuteError<F>> where W: FnOnce(F) -> Job, {
+        // Workers keep draining until every deque is empty, so only outside jobs are refused
+        let pool = Arc::as_ptr(&self.shared);
+        let job = LOCAL.with(|local| match local.borrow().as_ref() {
+            Some(local) if local.pool == pool => {
+                local.deque.push(wrap(f));
+                Ok(())
+            }
+            _ if self.shared.shutdown.load(Ordering::SeqCst) => Err(ExecuteError::Shutdown(f)),
+            _ => {
+                self.shared.injector.push(wrap(f));
+                Ok(())
+            }
+        });
+        job?;
+        self.shared.wake_one();
+        Ok(())
+    }
+}
+
+impl StealShared {
+    fn run_worker(&self, index: usize, deque: Worker<Job>) {
+        LOCAL.with(|local| {
+            *local.borrow_mut() = Some(LocalQueue {
+                pool: self as *const StealShared,
+                deque,
+            })
+        });
+        loop {
+            let job = LOCAL.with(|local| self.find_job(index, &local.borrow().as_ref().unwrap().deque));
+            match job {
+                Some(job) => {
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        self.panic_count.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+                None => {
+                    if self.sleep(index) {
+                        break;
+                    }
+                }
+            }
+        }
+        LOCAL.with(|local| local.borrow_mut().take());
+    }
+
+    /// Pop from the local deque, then steal from the injector and the other workers
+    fn find_job(&self, index: usize, deque: &Worker<Job>) -> Option<Job> {
+        deque.pop().or_else(|| {
+            iter::repeat_with(|| {
+                self.injector
+                    .steal_batch_and_pop(deque)
+                    .or_else(|| self.steal_from_others(index))
+            })
+            .find(|steal| !steal.is_retry())
+            .and_then(Steal::success)
+        })
+    }
+
+    fn steal_from_others(&self, index: usize) -> Steal<Job> {
+        let count = self.stealers.len();
+        (1..count)
+            .map(|offset| self.stealers[(index + offset) % count].steal())
+            .collect()
+    }
+
+    fn has_work(&self, index: usize) -> bool {
+        !self.injector.is_empty()
+            || self.stealers.iter().enumerate().any(|(i, stealer)| i != index && !stealer.is_empty())
+    }
+
+    /// Park until there is work to steal, returns true once the pool
+    /// is shutting down and there is nothing left to run
+    fn sleep(&self, index: usize) -> bool {
+        let mut guard = self.sleep_lock.lock().unwrap();
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        // Pairs with the fence in wake_one so a job pushed concurrently is either
+        // seen here or the pusher sees this worker sleeping
+        atomic::fence(Ordering::SeqCst);
+        let exit = loop {
+            if self.has_work(index) {
+                break false;
+            }
+            if self.shutdown.load(Ordering::SeqCst) {
+                break true;
+            }
+            guard = self.wake.wait(guard).unwrap();
+        };
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        exit
+    }
+
+    fn wake_one(&self) {
+        atomic::fence(Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _guard = self.sleep_lock.lock().unwrap();
+            self.wake.notify_one();
+        }
+    }
+}
+
+impl Debug for WorkStealingPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkStealingPool")
+            .field("num_threads", &self.threads.len())
+            .field("panic_count", &self.shared.panic_count)
+            .finish()
+    }
+}
+
+/// Graceful shutdown mechanism,
+/// workers drain every deque before exiting
+impl Drop for WorkStealingPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        {
+            let _guard = self.shared.sleep_lock.lock().unwrap();
+            self.shared.wake.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
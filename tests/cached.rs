@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_threadpool::{CachedThreadPool, TaskError};
+
+/// Poll until `done` holds, panics after a few seconds
+fn wait_until<F>(done: F) where F: Fn() -> bool, {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "condition never held");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn invalid_sizes_are_rejected() {
+    assert!(CachedThreadPool::new(0, 0, Duration::from_secs(1)).is_err());
+    assert!(CachedThreadPool::new(3, 2, Duration::from_secs(1)).is_err());
+    assert!(CachedThreadPool::new(0, 1, Duration::from_secs(1)).is_ok());
+}
+
+#[test]
+fn workers_are_spawned_on_demand_up_to_max_threads() {
+    let pool = CachedThreadPool::new(1, 4, Duration::from_secs(60)).unwrap();
+    assert_eq!(pool.num_threads(), 0);
+
+    let barrier = Arc::new(Barrier::new(5));
+    for _ in 0..4 {
+        let barrier = Arc::clone(&barrier);
+        pool.execute(move || {
+            barrier.wait();
+        })
+        .unwrap();
+    }
+    // All four jobs run at once, so each got its own worker
+    barrier.wait();
+    assert_eq!(pool.num_threads(), 4);
+
+    // Past max_threads jobs queue for the existing workers
+    let handles: Vec<_> = (0..8).map(|i| pool.submit(move || i).unwrap()).collect();
+    assert_eq!(pool.num_threads(), 4);
+    assert_eq!(handles.into_iter().map(|handle| handle.join().unwrap()).sum::<i32>(), 28);
+}
+
+#[test]
+fn idle_workers_above_core_retire_after_keep_alive() {
+    let pool = CachedThreadPool::new(1, 3, Duration::from_millis(50)).unwrap();
+    let barrier = Arc::new(Barrier::new(4));
+    for _ in 0..3 {
+        let barrier = Arc::clone(&barrier);
+        pool.execute(move || {
+            barrier.wait();
+        })
+        .unwrap();
+    }
+    barrier.wait();
+    assert_eq!(pool.num_threads(), 3);
+
+    wait_until(|| pool.num_threads() == 1);
+    // The core worker stays past further keep-alive periods and picks up new jobs
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(pool.num_threads(), 1);
+    assert_eq!(pool.submit(|| 7).unwrap().join(), Ok(7));
+    assert_eq!(pool.num_threads(), 1);
+}
+
+#[test]
+fn idle_worker_is_reused_instead_of_spawning() {
+    let pool = CachedThreadPool::new(0, 4, Duration::from_secs(60)).unwrap();
+    for _ in 0..5 {
+        pool.submit(|| ()).unwrap().join().unwrap();
+        // The worker goes back to waiting just after the handle resolves
+        wait_until(|| pool.idle_threads() == 1);
+    }
+    assert_eq!(pool.num_threads(), 1);
+}
+
+#[test]
+fn panics_are_counted_and_reported() {
+    let pool = CachedThreadPool::new(1, 2, Duration::from_secs(60)).unwrap();
+    pool.execute(|| panic!("boom")).unwrap();
+    let handle = pool.submit(|| -> i32 { panic!("boom") }).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::Panicked(String::from("boom"))));
+    wait_until(|| pool.panic_count() == 2);
+    assert_eq!(pool.submit(|| 1).unwrap().join(), Ok(1));
+}
+
+#[test]
+fn drop_runs_queued_jobs() {
+    let ran = Arc::new(AtomicUsize::new(0));
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    {
+        let pool = CachedThreadPool::new(0, 1, Duration::from_secs(60)).unwrap();
+        pool.execute(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+        for _ in 0..5 {
+            let ran = Arc::clone(&ran);
+            pool.execute(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        drop(release_tx);
+    }
+    assert_eq!(ran.load(Ordering::SeqCst), 5);
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use common::block_worker;
+use rust_threadpool::{CancellationToken, TaskError, ThreadPool};
+
+#[test]
+fn uncancelled_token_does_not_affect_the_job() {
+    let pool = ThreadPool::new(1).unwrap();
+    let handle = pool.submit_with_token(CancellationToken::new(), |_| 7).unwrap();
+    assert_eq!(handle.join(), Ok(7));
+}
+
+#[test]
+fn cancelling_a_queued_job_resolves_its_handle_right_away() {
+    let pool = ThreadPool::new(1).unwrap();
+    let release = block_worker(&pool);
+    let ran = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&ran);
+    let token = CancellationToken::new();
+    let mut handle = pool
+        .submit_with_token(token.clone(), move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    assert_eq!(handle.try_join(), None);
+
+    token.cancel();
+    // The job is still queued behind the blocked worker
+    assert_eq!(handle.join_timeout(Duration::from_millis(50)), Some(Err(TaskError::Cancelled)));
+
+    drop(release);
+    pool.wait_idle();
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn job_submitted_with_a_cancelled_token_is_resolved_and_skipped() {
+    let pool = ThreadPool::new(1).unwrap();
+    let release = block_worker(&pool);
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut handle = pool.submit_with_token(token, |_| panic!("should be skipped")).unwrap();
+    assert_eq!(handle.try_join(), Some(Err(TaskError::Cancelled)));
+    drop(release);
+    pool.wait_idle();
+    assert_eq!(pool.panic_count(), 0);
+}
+
+#[test]
+fn cancelling_a_running_job_resolves_its_handle_before_it_returns() {
+    let pool = ThreadPool::new(1).unwrap();
+    let token = CancellationToken::new();
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut handle = pool
+        .submit_with_token(token.clone(), move |_| {
+            started_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+            1
+        })
+        .unwrap();
+    started_rx.recv().unwrap();
+
+    token.cancel();
+    assert_eq!(handle.join_timeout(Duration::from_millis(50)), Some(Err(TaskError::Cancelled)));
+    // The job keeps its worker until it returns
+    assert_eq!(pool.stats().active_workers, 1);
+    drop(release_tx);
+    pool.wait_idle();
+}
+
+#[test]
+fn running_job_sees_the_cancellation() {
+    let pool = ThreadPool::new(1).unwrap();
+    let token = CancellationToken::new();
+    let (done_tx, done_rx) = mpsc::channel();
+    let handle = pool
+        .submit_with_token(token.clone(), move |token| {
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            done_tx.send(()).unwrap();
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(10));
+    token.cancel();
+    assert_eq!(handle.join(), Err(TaskError::Cancelled));
+    done_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn one_token_cancels_every_task_it_was_given() {
+    let pool = ThreadPool::new(1).unwrap();
+    let release = block_worker(&pool);
+    let token = CancellationToken::new();
+    // Finished tasks are swept from the token as more are registered
+    for i in 0..100 {
+        let handle = pool.submit_with_token(token.clone(), move |_| i).unwrap();
+        drop(handle);
+    }
+    let handles: Vec<_> = (0..10).map(|i| pool.submit_with_token(token.clone(), move |_| i).unwrap()).collect();
+    let clone = token.clone();
+    clone.cancel();
+    assert!(token.is_cancelled());
+    for handle in handles {
+        assert_eq!(handle.join(), Err(TaskError::Cancelled));
+    }
+    drop(release);
+    pool.wait_idle();
+}
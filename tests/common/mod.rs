@@ -0,0 +1,36 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use std::any::Any;
+use std::sync::mpsc;
+
+use rust_threadpool::ThreadPool;
+
+/// Occupy one worker until the returned sender is dropped
+pub fn block_worker(pool: &ThreadPool) -> mpsc::Sender<()> {
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || {
+        started_tx.send(()).unwrap();
+        let _ = release_rx.recv();
+    })
+    .unwrap();
+    started_rx.recv().unwrap();
+    release_tx
+}
+
+/// Occupy the only worker until the returned sender is dropped, then fill the one queue slot
+pub fn saturate(pool: &ThreadPool) -> mpsc::Sender<()> {
+    let release = block_worker(pool);
+    pool.execute(|| {}).unwrap();
+    release
+}
+
+/// Message carried by a panic payload, empty for payloads that are not strings
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("")
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use common::block_worker;
+use rust_threadpool::{Completed, CompletionService, ExecuteError, TaskError, ThreadPool};
+
+#[test]
+fn results_come_back_in_the_order_jobs_finish() {
+    let pool = ThreadPool::new(3).unwrap();
+    let mut service = CompletionService::new(&pool);
+    let mut releases = Vec::new();
+    for i in 0..3 {
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        releases.push(release_tx);
+        assert_eq!(
+            service
+                .submit(move || {
+                    let _ = release_rx.recv();
+                    i
+                })
+                .unwrap(),
+            i
+        );
+    }
+    assert_eq!(service.pending(), 3);
+    assert!(service.poll().is_none());
+
+    // Release the jobs back to front
+    while let Some(release) = releases.pop() {
+        drop(release);
+        let completed = service.poll_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(completed.index, releases.len());
+        assert_eq!(completed.result, Ok(releases.len()));
+    }
+    assert_eq!(service.pending(), 0);
+    assert!(service.take().is_none());
+    assert!(service.poll_timeout(Duration::from_millis(1)).is_none());
+}
+
+#[test]
+fn panics_are_reported_with_their_index() {
+    let pool = ThreadPool::new(2).unwrap();
+    let mut service = CompletionService::new(&pool);
+    service.submit(|| 1).unwrap();
+    service.submit(|| -> i32 { panic!("boom") }).unwrap();
+    let mut results: Vec<_> = service.iter().collect();
+    results.sort_by_key(|completed| completed.index);
+    assert_eq!(
+        results,
+        [
+            Completed { index: 0, result: Ok(1) },
+            Completed { index: 1, result: Err(TaskError::Panicked(String::from("boom"))) },
+        ]
+    );
+}
+
+#[test]
+fn jobs_dropped_without_running_are_reported_as_discarded() {
+    let pool = ThreadPool::new(1).unwrap();
+    let mut service = CompletionService::new(&pool);
+    let release = block_worker(&pool);
+    service.submit(|| 1).unwrap();
+    assert_eq!(pool.shutdown_now().len(), 1);
+    drop(release);
+    assert_eq!(service.take(), Some(Completed { index: 0, result: Err(TaskError::Discarded) }));
+    assert!(service.take().is_none());
+}
+
+#[test]
+fn submit_rejected_after_shutdown_reports_nothing() {
+    let pool = ThreadPool::new(1).unwrap();
+    let mut service = CompletionService::new(&pool);
+    assert_eq!(service.submit(|| 0).unwrap(), 0);
+    pool.shutdown();
+    assert!(matches!(service.submit(|| 1), Err(ExecuteError::Shutdown(_))));
+    assert_eq!(service.pending(), 1);
+    let results: Vec<_> = service.iter().collect();
+    assert_eq!(results, [Completed { index: 0, result: Ok(0) }]);
+    assert!(service.poll().is_none());
+}
+
+#[test]
+fn submit_rejected_by_a_full_queue_does_not_use_up_an_index() {
+    let pool = ThreadPool::builder().num_threads(1).queue_capacity(1).build().unwrap();
+    let mut service = CompletionService::new(&pool);
+    let release = block_worker(&pool);
+    assert_eq!(service.submit(|| 0).unwrap(), 0);
+    assert!(matches!(service.submit(|| 1), Err(ExecuteError::QueueFull(_))));
+    drop(release);
+    assert_eq!(service.take(), Some(Completed { index: 0, result: Ok(0) }));
+    assert!(service.poll().is_none());
+
+    assert_eq!(service.submit(|| 2).unwrap(), 1);
+    assert_eq!(service.take(), Some(Completed { index: 1, result: Ok(2) }));
+    assert!(service.take().is_none());
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use common::saturate;
+use rust_threadpool::{CancellationToken, RejectionPolicy, TaskError, ThreadPool};
+
+/// Pool with one worker and one queue slot, both taken until the returned sender is dropped
+fn saturated_caller_runs_pool() -> (ThreadPool, mpsc::Sender<()>) {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::CallerRuns)
+        .build()
+        .unwrap();
+    let release = saturate(&pool);
+    (pool, release)
+}
+
+#[test]
+fn invoke_all_keeps_submission_order() {
+    let pool = ThreadPool::new(3).unwrap();
+    let results = pool.invoke_all((0..10usize).map(|i| move || i * i));
+    assert_eq!(results, (0..10usize).map(|i| Ok(i * i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn invoke_all_reports_panics_under_caller_runs() {
+    let (pool, release) = saturated_caller_runs_pool();
+    let results = pool.invoke_all(vec![|| -> i32 { panic!("boom") }]);
+    assert_eq!(results, vec![Err(TaskError::Panicked(String::from("boom")))]);
+    assert_eq!(pool.panic_count(), 1);
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn invoke_any_reports_panics_under_caller_runs() {
+    let (pool, release) = saturated_caller_runs_pool();
+    let result = pool.invoke_any(vec![|_: &CancellationToken| -> i32 { panic!("boom") }]);
+    let error = result.unwrap_err();
+    assert_eq!(error.errors(), [TaskError::Panicked(String::from("boom"))]);
+    assert_eq!(pool.panic_count(), 1);
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn invoke_any_returns_a_success_after_a_panic_under_caller_runs() {
+    let (pool, release) = saturated_caller_runs_pool();
+    let tasks = (0..2).map(|i| move |_: &CancellationToken| if i == 0 { panic!("boom") } else { 7 });
+    assert_eq!(pool.invoke_any(tasks), Ok(7));
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
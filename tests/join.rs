@@ -0,0 +1,187 @@
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{block_worker, panic_message};
+use rust_threadpool::{RejectionPolicy, TaskError, ThreadPool};
+
+fn fib(pool: &ThreadPool, n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let (a, b) = pool.join(|| fib(pool, n - 1), || fib(pool, n - 2));
+    a + b
+}
+
+#[test]
+fn join_borrows_from_the_caller() {
+    let pool = ThreadPool::new(2).unwrap();
+    let mut left = vec![1, 2, 3];
+    let mut right = vec![4, 5, 6];
+    let (a, b) = pool.join(
+        || {
+            left.push(0);
+            left.len()
+        },
+        || {
+            right.clear();
+            right.len()
+        },
+    );
+    assert_eq!((a, b), (4, 0));
+    assert_eq!(left, [1, 2, 3, 0]);
+    assert!(right.is_empty());
+}
+
+#[test]
+fn recursive_join_from_inside_pool_jobs() {
+    let pool = Arc::new(ThreadPool::new(2).unwrap());
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let pool = Arc::clone(&pool);
+            pool.clone().submit(move || fib(&pool, 18)).unwrap()
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join(), Ok(2584));
+    }
+}
+
+#[test]
+fn panic_in_either_closure_is_resumed_after_both_finish() {
+    let pool = ThreadPool::new(2).unwrap();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(|| panic!("left"), || 1)));
+    assert_eq!(panic_message(result.unwrap_err().as_ref()), "left");
+
+    let mut ran = false;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(|| ran = true, || -> i32 { panic!("right") })));
+    assert_eq!(panic_message(result.unwrap_err().as_ref()), "right");
+    assert!(ran);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| pool.join(|| panic!("left"), || -> i32 { panic!("right") })));
+    assert_eq!(panic_message(result.unwrap_err().as_ref()), "left");
+
+    // The pool is left usable
+    assert_eq!(pool.join(|| 1, || 2), (1, 2));
+}
+
+#[test]
+fn join_on_a_paused_pool_runs_both_closures_on_the_caller() {
+    let pool = ThreadPool::new(2).unwrap();
+    pool.pause();
+    let caller = thread::current().id();
+    let (a, b) = pool.join(|| thread::current().id(), || thread::current().id());
+    assert_eq!((a, b), (caller, caller));
+    // The queued copy of `b` finds it already taken once the pool resumes
+    pool.resume();
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn join_on_a_shut_down_pool_runs_both_closures_on_the_caller() {
+    let pool = ThreadPool::new(2).unwrap();
+    pool.shutdown();
+    let mut value = 0;
+    let (a, b) = pool.join(|| 1, || {
+        value = 2;
+        value
+    });
+    assert_eq!((a, b), (1, 2));
+    assert_eq!(value, 2);
+}
+
+#[test]
+fn worker_replaced_while_helping_in_join_retires() {
+    let pool = Arc::new(ThreadPool::builder().num_threads(2).replace_timed_out_workers(true).build().unwrap());
+    let (done_tx, done_rx) = mpsc::channel();
+    let outer_pool = Arc::clone(&pool);
+    pool.execute(move || {
+        let pool = &outer_pool;
+        let (b_started_tx, b_started_rx) = mpsc::channel();
+        let (slow_done_tx, slow_done_rx) = mpsc::channel::<()>();
+        let (slow, ()) = pool.join(
+            || {
+                // Queued behind `b`, so the other worker takes `b` and this one runs the slow job while helping
+                let slow = pool
+                    .submit_with_timeout(Duration::from_millis(10), move |_| {
+                        thread::sleep(Duration::from_millis(60));
+                        drop(slow_done_tx);
+                    })
+                    .unwrap();
+                b_started_rx.recv().unwrap();
+                slow
+            },
+            move || {
+                b_started_tx.send(()).unwrap();
+                // Held until the slow job returned on the helping worker
+                let _ = slow_done_rx.recv();
+            },
+        );
+        done_tx.send(slow.join()).unwrap();
+    })
+    .unwrap();
+    assert_eq!(done_rx.recv_timeout(Duration::from_secs(5)).unwrap(), Err(TaskError::TimedOut));
+
+    // The helping worker leaves once its own job returns, the replacement takes its place
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let ids: Vec<usize> = pool.stats().workers.iter().map(|worker| worker.id).collect();
+        if ids.len() == 2 && ids.contains(&2) {
+            break;
+        }
+        assert!(Instant::now() < deadline, "workers never settled: {:?}", ids);
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(pool.num_threads(), 2);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn join_on_a_full_queue_runs_both_closures_on_the_caller_under_every_policy() {
+    for policy in [RejectionPolicy::Block, RejectionPolicy::Abort, RejectionPolicy::CallerRuns, RejectionPolicy::DiscardOldest] {
+        let pool = ThreadPool::builder().num_threads(1).queue_capacity(1).rejection_policy(policy).build().unwrap();
+        let release = block_worker(&pool);
+        let ran = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&ran);
+        pool.execute(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        // Neither blocks for room nor evicts the queued job
+        let caller = thread::current().id();
+        let (a, b) = pool.join(|| thread::current().id(), || thread::current().id());
+        assert_eq!((a, b), (caller, caller), "{:?}", policy);
+
+        drop(release);
+        assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+        assert_eq!(ran.load(Ordering::SeqCst), 1, "{:?}", policy);
+    }
+}
+
+#[test]
+fn recursive_join_on_a_small_blocking_queue_does_not_deadlock() {
+    let pool = Arc::new(ThreadPool::builder().num_threads(2).queue_capacity(1).rejection_policy(RejectionPolicy::Block).build().unwrap());
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let pool = Arc::clone(&pool);
+            pool.clone().submit(move || fib(&pool, 15)).unwrap()
+        })
+        .collect();
+    for mut handle in handles {
+        assert_eq!(handle.join_timeout(Duration::from_secs(10)), Some(Ok(610)));
+    }
+}
+
+#[test]
+fn recursive_join_on_a_small_discarding_queue_loses_no_work() {
+    let pool = Arc::new(ThreadPool::builder().num_threads(2).queue_capacity(1).rejection_policy(RejectionPolicy::DiscardOldest).build().unwrap());
+    let inner = Arc::clone(&pool);
+    let mut handle = pool.submit(move || fib(&inner, 15)).unwrap();
+    assert_eq!(handle.join_timeout(Duration::from_secs(10)), Some(Ok(610)));
+}
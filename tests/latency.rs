@@ -0,0 +1,87 @@
+use std::thread;
+use std::time::Duration;
+
+use rust_threadpool::{HistogramMode, ThreadPool};
+
+fn pool_with_histograms(mode: HistogramMode) -> ThreadPool {
+    ThreadPool::builder().num_threads(1).latency_histograms(mode).build().unwrap()
+}
+
+#[test]
+fn histograms_are_off_by_default() {
+    assert!(ThreadPool::new(1).unwrap().latency().is_none());
+}
+
+#[test]
+fn run_time_percentiles_separate_slow_jobs_from_fast_ones() {
+    let pool = pool_with_histograms(HistogramMode::Cumulative);
+    for i in 0..100 {
+        pool.execute(move || {
+            if i % 10 == 0 {
+                thread::sleep(Duration::from_millis(20));
+            }
+        })
+        .unwrap();
+    }
+    pool.wait_idle();
+
+    let run_time = pool.latency().unwrap().run_time;
+    assert_eq!(run_time.count(), 100);
+    assert!(run_time.p50() < Duration::from_millis(5), "{:?}", run_time);
+    // The slowest tenth sets p99, reported within the bucket precision of the real value
+    assert!(run_time.p99() >= Duration::from_millis(19), "{:?}", run_time);
+    assert!(run_time.p99() <= run_time.max());
+    assert!(run_time.p999() <= run_time.max());
+    assert!(run_time.max() >= Duration::from_millis(20));
+    assert!(run_time.sum() >= Duration::from_millis(200));
+    assert!(run_time.mean() >= Duration::from_millis(2));
+    assert!((90..100).contains(&run_time.count_at_or_below(Duration::from_millis(5))));
+    assert_eq!(run_time.count_at_or_below(Duration::from_secs(60)), 100);
+}
+
+#[test]
+fn queue_wait_covers_the_time_spent_behind_a_busy_worker() {
+    let pool = pool_with_histograms(HistogramMode::Cumulative);
+    pool.execute(|| thread::sleep(Duration::from_millis(30))).unwrap();
+    pool.execute(|| {}).unwrap();
+    pool.wait_idle();
+
+    let queue_wait = pool.latency().unwrap().queue_wait;
+    assert_eq!(queue_wait.count(), 2);
+    assert!(queue_wait.max() >= Duration::from_millis(25), "{:?}", queue_wait);
+}
+
+#[test]
+fn cumulative_histograms_keep_their_values_across_reads() {
+    let pool = pool_with_histograms(HistogramMode::Cumulative);
+    for _ in 0..10 {
+        pool.execute(|| {}).unwrap();
+    }
+    pool.wait_idle();
+    let first = pool.latency().unwrap();
+    assert_eq!(first.run_time.count(), 10);
+    assert_eq!(pool.latency().unwrap(), first);
+
+    pool.execute(|| {}).unwrap();
+    pool.wait_idle();
+    assert_eq!(pool.latency().unwrap().run_time.count(), 11);
+}
+
+#[test]
+fn reset_on_read_histograms_start_over_after_each_read() {
+    let pool = pool_with_histograms(HistogramMode::ResetOnRead);
+    for _ in 0..10 {
+        pool.execute(|| {}).unwrap();
+    }
+    pool.wait_idle();
+    assert_eq!(pool.latency().unwrap().run_time.count(), 10);
+
+    let empty = pool.latency().unwrap();
+    assert_eq!((empty.run_time.count(), empty.queue_wait.count()), (0, 0));
+    assert_eq!(empty.run_time.p99(), Duration::ZERO);
+    assert_eq!(empty.run_time.mean(), Duration::ZERO);
+
+    pool.execute(|| {}).unwrap();
+    pool.wait_idle();
+    assert_eq!(pool.latency().unwrap().run_time.count(), 1);
+}
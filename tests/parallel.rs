@@ -0,0 +1,33 @@
+use rust_threadpool::{ParallelSource, ThreadPool};
+
+#[test]
+fn par_map_over_signed_range_crossing_zero() {
+    let pool = ThreadPool::new(3).unwrap();
+    assert_eq!(pool.par_map(-5i32..5, |i| i * 2), (-5i32..5).map(|i| i * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn full_width_i32_range_is_chunked_without_overflow() {
+    let pool = ThreadPool::new(3).unwrap();
+    let source = i32::MIN..i32::MAX;
+    assert_eq!(ParallelSource::len(&source), u32::MAX as usize);
+    let chunks = pool.par_chunks(source, 1 << 28, |chunk| chunk);
+    assert_eq!(chunks.len(), 16);
+    assert_eq!(chunks.first().unwrap().start, i32::MIN);
+    assert_eq!(chunks.last().unwrap().end, i32::MAX);
+    for pair in chunks.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+}
+
+#[test]
+fn full_width_i64_range_is_chunked_without_overflow() {
+    let pool = ThreadPool::new(3).unwrap();
+    let chunks = pool.par_chunks(i64::MIN..i64::MAX, 1 << 62, |chunk| chunk);
+    assert_eq!(chunks.len(), 4);
+    assert_eq!(chunks.first().unwrap().start, i64::MIN);
+    assert_eq!(chunks.last().unwrap().end, i64::MAX);
+    for pair in chunks.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+}
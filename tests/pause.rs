@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rust_threadpool::ThreadPool;
+
+#[test]
+fn paused_pool_with_queued_jobs_is_not_idle_until_resumed() {
+    let pool = ThreadPool::new(2).unwrap();
+    pool.pause();
+    let ran = Arc::new(AtomicUsize::new(0));
+    for _ in 0..4 {
+        let ran = Arc::clone(&ran);
+        pool.execute(move || {
+            ran.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    }
+    assert!(pool.await_in_flight(Duration::from_secs(5)));
+    assert!(!pool.wait_idle_timeout(Duration::from_millis(20)));
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+
+    pool.resume();
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+    assert_eq!(ran.load(Ordering::SeqCst), 4);
+}
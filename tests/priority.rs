@@ -0,0 +1,87 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::block_worker;
+use rust_threadpool::{Priority, ThreadPool};
+
+/// Queue `jobs` on a single worker pool while its worker is held, then return the order they ran in
+fn run_order(pool: &ThreadPool, jobs: &[(Priority, &'static str)]) -> Vec<&'static str> {
+    let release = block_worker(pool);
+    let order = Arc::new(Mutex::new(Vec::new()));
+    for &(priority, tag) in jobs {
+        let order = Arc::clone(&order);
+        pool.execute_with_priority(priority, move || order.lock().unwrap().push(tag)).unwrap();
+    }
+    drop(release);
+    pool.wait_idle();
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[test]
+fn higher_priorities_run_first_and_each_level_is_fifo() {
+    let pool = ThreadPool::builder().num_threads(1).priority_aging(0).build().unwrap();
+    let order = run_order(
+        &pool,
+        &[
+            (Priority::Low, "L1"),
+            (Priority::Normal, "N1"),
+            (Priority::High, "H1"),
+            (Priority::Normal, "N2"),
+            (Priority::High, "H2"),
+            (Priority::Low, "L2"),
+        ],
+    );
+    assert_eq!(order, ["H1", "H2", "N1", "N2", "L1", "L2"]);
+}
+
+#[test]
+fn execute_queues_at_normal_priority() {
+    let pool = ThreadPool::builder().num_threads(1).priority_aging(0).build().unwrap();
+    let release = block_worker(&pool);
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let push = |tag| {
+        let order = Arc::clone(&order);
+        move || order.lock().unwrap().push(tag)
+    };
+    pool.execute_with_priority(Priority::Low, push("low")).unwrap();
+    pool.execute(push("default")).unwrap();
+    pool.execute_with_priority(Priority::High, push("high")).unwrap();
+    drop(release);
+    pool.wait_idle();
+    assert_eq!(*order.lock().unwrap(), ["high", "default", "low"]);
+}
+
+#[test]
+fn passed_over_levels_age_into_the_next_pick() {
+    let pool = ThreadPool::builder().num_threads(1).priority_aging(2).build().unwrap();
+    let order = run_order(
+        &pool,
+        &[
+            (Priority::High, "H1"),
+            (Priority::High, "H2"),
+            (Priority::High, "H3"),
+            (Priority::High, "H4"),
+            (Priority::High, "H5"),
+            (Priority::High, "H6"),
+            (Priority::Normal, "N"),
+            (Priority::Low, "L"),
+        ],
+    );
+    // Both lower levels were passed over twice, so they run before the remaining high jobs
+    assert_eq!(order[..2], ["H1", "H2"]);
+    let mut aged = order[2..4].to_vec();
+    aged.sort();
+    assert_eq!(aged, ["L", "N"]);
+    assert_eq!(order[4..], ["H3", "H4", "H5", "H6"]);
+}
+
+#[test]
+fn without_aging_lower_levels_wait_for_higher_ones() {
+    let pool = ThreadPool::builder().num_threads(1).priority_aging(0).build().unwrap();
+    let mut jobs: Vec<(Priority, &'static str)> = vec![(Priority::Low, "L")];
+    jobs.extend((0..50).map(|_| (Priority::High, "H")));
+    let order = run_order(&pool, &jobs);
+    assert_eq!(order.last(), Some(&"L"));
+}
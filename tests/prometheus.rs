@@ -0,0 +1,126 @@
+#![cfg(feature = "prometheus")]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use rust_threadpool::{prometheus, HistogramMode, ThreadPool};
+
+/// Value of the sample whose name and labels are `series`, e.g. `jobs_total{pool="main"}`
+fn sample(text: &str, series: &str) -> Option<f64> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix(series))
+        .find_map(|value| value.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+fn get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// Drop `server` on another thread, failing the test if that does not return promptly
+fn assert_drop_returns(server: prometheus::MetricsServer) {
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        drop(server);
+        done_tx.send(()).unwrap();
+    });
+    done_rx.recv_timeout(Duration::from_secs(5)).expect("dropping the server hung");
+}
+
+#[test]
+fn render_exports_pool_counters() {
+    let pool = ThreadPool::new(2).unwrap();
+    for _ in 0..10 {
+        pool.execute(|| {}).unwrap();
+    }
+    pool.execute(|| panic!("boom")).unwrap();
+    pool.wait_idle();
+
+    let text = prometheus::render(&pool, "main");
+    assert!(text.contains("# TYPE threadpool_jobs_submitted_total counter\n"));
+    assert_eq!(sample(&text, "threadpool_jobs_submitted_total{pool=\"main\"}"), Some(11.0));
+    assert_eq!(sample(&text, "threadpool_jobs_completed_total{pool=\"main\"}"), Some(11.0));
+    assert_eq!(sample(&text, "threadpool_jobs_panicked_total{pool=\"main\"}"), Some(1.0));
+    assert_eq!(sample(&text, "threadpool_queued_jobs{pool=\"main\"}"), Some(0.0));
+    assert_eq!(sample(&text, "threadpool_idle_workers{pool=\"main\"}"), Some(2.0));
+    let per_worker: f64 = (0..2)
+        .filter_map(|id| sample(&text, &format!("threadpool_worker_jobs_completed_total{{pool=\"main\",worker=\"{}\"}}", id)))
+        .sum();
+    assert_eq!(per_worker, 11.0);
+}
+
+#[test]
+fn pool_name_is_escaped() {
+    let pool = ThreadPool::new(1).unwrap();
+    let text = prometheus::render(&pool, "a \"quoted\"\\name\n");
+    assert!(text.contains("threadpool_queued_jobs{pool=\"a \\\"quoted\\\"\\\\name\\n\"} 0\n"), "{}", text);
+}
+
+#[test]
+fn busy_time_and_histograms_are_only_exported_when_enabled() {
+    let pool = ThreadPool::new(1).unwrap();
+    pool.execute(|| {}).unwrap();
+    pool.wait_idle();
+    let text = prometheus::render(&pool, "main");
+    assert!(!text.contains("threadpool_worker_busy_seconds_total"));
+    assert!(!text.contains("threadpool_run_time_seconds"));
+    // Rendering does not switch busy time on
+    assert!(!prometheus::render(&pool, "main").contains("threadpool_worker_busy_seconds_total"));
+
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .busy_time(true)
+        .latency_histograms(HistogramMode::Cumulative)
+        .build()
+        .unwrap();
+    for _ in 0..5 {
+        pool.execute(|| thread::sleep(Duration::from_millis(2))).unwrap();
+    }
+    pool.wait_idle();
+    let text = prometheus::render(&pool, "main");
+    let busy = sample(&text, "threadpool_worker_busy_seconds_total{pool=\"main\",worker=\"0\"}").unwrap();
+    assert!(busy >= 0.01, "{}", busy);
+    assert!(text.contains("# TYPE threadpool_run_time_seconds histogram\n"));
+    assert_eq!(sample(&text, "threadpool_run_time_seconds_bucket{pool=\"main\",le=\"+Inf\"}"), Some(5.0));
+    assert_eq!(sample(&text, "threadpool_run_time_seconds_count{pool=\"main\"}"), Some(5.0));
+    assert_eq!(sample(&text, "threadpool_run_time_seconds_bucket{pool=\"main\",le=\"0.001\"}"), Some(0.0));
+    assert_eq!(sample(&text, "threadpool_run_time_seconds_bucket{pool=\"main\",le=\"1\"}"), Some(5.0));
+    assert_eq!(sample(&text, "threadpool_queue_wait_seconds_count{pool=\"main\"}"), Some(5.0));
+}
+
+#[test]
+fn server_answers_metrics_requests() {
+    let pool = Arc::new(ThreadPool::new(1).unwrap());
+    pool.execute(|| {}).unwrap();
+    pool.wait_idle();
+    let server = prometheus::serve(Arc::clone(&pool), "main", "127.0.0.1:0").unwrap();
+
+    let response = get(server.local_addr(), "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    assert!(response.contains("threadpool_jobs_completed_total{pool=\"main\"} 1\n"));
+
+    let response = get(server.local_addr(), "/other");
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", response);
+    assert_drop_returns(server);
+}
+
+#[test]
+fn server_bound_to_the_unspecified_address_stops_when_dropped() {
+    let pool = Arc::new(ThreadPool::new(1).unwrap());
+    let server = prometheus::serve(Arc::clone(&pool), "main", "0.0.0.0:0").unwrap();
+    assert_drop_returns(server);
+
+    // Only where the host has IPv6
+    if TcpListener::bind("[::1]:0").is_ok() {
+        let server = prometheus::serve(pool, "main", "[::]:0").unwrap();
+        assert_drop_returns(server);
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use rust_threadpool::{RejectionPolicy, ThreadPool};
+
+const PRODUCERS: usize = 4;
+const JOBS_PER_PRODUCER: usize = 2000;
+
+#[test]
+fn concurrent_producers_under_every_rejection_policy() {
+    for policy in [
+        RejectionPolicy::Block,
+        RejectionPolicy::Abort,
+        RejectionPolicy::DiscardOldest,
+        RejectionPolicy::CallerRuns,
+    ] {
+        for _ in 0..5 {
+            let ran = Arc::new(AtomicUsize::new(0));
+            let accepted = Arc::new(AtomicUsize::new(0));
+            let pool = Arc::new(
+                ThreadPool::builder()
+                    .num_threads(4)
+                    .queue_capacity(8)
+                    .rejection_policy(policy)
+                    .build()
+                    .unwrap(),
+            );
+            let producers: Vec<_> = (0..PRODUCERS)
+                .map(|_| {
+                    let (pool, ran, accepted) = (Arc::clone(&pool), Arc::clone(&ran), Arc::clone(&accepted));
+                    thread::spawn(move || {
+                        for _ in 0..JOBS_PER_PRODUCER {
+                            let ran = Arc::clone(&ran);
+                            let job = move || {
+                                ran.fetch_add(1, Ordering::SeqCst);
+                            };
+                            if pool.execute(job).is_ok() {
+                                accepted.fetch_add(1, Ordering::SeqCst);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            pool.shutdown();
+            assert!(pool.await_termination(Duration::from_secs(10)), "{:?} pool did not drain", policy);
+            let (ran, accepted) = (ran.load(Ordering::SeqCst), accepted.load(Ordering::SeqCst));
+            match policy {
+                RejectionPolicy::Block => assert_eq!(ran, PRODUCERS * JOBS_PER_PRODUCER),
+                RejectionPolicy::DiscardOldest => assert!(ran <= accepted),
+                _ => assert_eq!(ran, accepted, "{:?} lost a job", policy),
+            }
+        }
+    }
+}
+
+/// Every push to a queue whose workers have gone to sleep must wake one of them,
+/// a lost wakeup leaves the job queued and the receive times out
+#[test]
+fn push_wakes_parked_workers() {
+    let pool = ThreadPool::new(4).unwrap();
+    let (done_tx, done_rx) = mpsc::channel();
+    for round in 0..200 {
+        // Long enough for idle workers to finish spinning and park
+        if round % 10 == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let done_tx = done_tx.clone();
+        pool.execute(move || done_tx.send(round).unwrap()).unwrap();
+        assert_eq!(done_rx.recv_timeout(Duration::from_secs(5)), Ok(round));
+    }
+}
+
+#[test]
+fn concurrent_pushes_wake_parked_workers() {
+    let pool = Arc::new(ThreadPool::new(4).unwrap());
+    for _ in 0..50 {
+        thread::sleep(Duration::from_millis(2));
+        let (done_tx, done_rx) = mpsc::channel();
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|_| {
+                let (pool, done_tx) = (Arc::clone(&pool), done_tx.clone());
+                thread::spawn(move || pool.execute(move || done_tx.send(()).unwrap()).unwrap())
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        for _ in 0..PRODUCERS {
+            assert!(done_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        }
+    }
+}
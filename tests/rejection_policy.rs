@@ -0,0 +1,79 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use common::saturate;
+use rust_threadpool::{PoolEvent, RejectionPolicy, TaskError, ThreadPool, CALLER_RUNS_WORKER};
+
+#[test]
+fn caller_runs_reports_panic_through_handle() {
+    let panics = Arc::new(AtomicUsize::new(0));
+    let seen = Arc::clone(&panics);
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::CallerRuns)
+        .panic_handler(move |worker, _| {
+            assert_eq!(worker, CALLER_RUNS_WORKER);
+            seen.fetch_add(1, Ordering::SeqCst);
+        })
+        .build()
+        .unwrap();
+    let release = saturate(&pool);
+
+    let handle = pool.submit(|| -> i32 { panic!("boom") }).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::Panicked(String::from("boom"))));
+    assert_eq!(pool.panic_count(), 1);
+    assert_eq!(panics.load(Ordering::SeqCst), 1);
+
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn caller_runs_execute_does_not_unwind() {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::CallerRuns)
+        .build()
+        .unwrap();
+    let release = saturate(&pool);
+
+    assert!(pool.execute(|| panic!("boom")).is_ok());
+    assert_eq!(pool.panic_count(), 1);
+
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+}
+
+#[test]
+fn caller_runs_job_is_reported_like_a_worker_job() {
+    let (events_tx, events_rx) = mpsc::channel();
+    let events_tx = Mutex::new(events_tx);
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::CallerRuns)
+        .event_sink(move |event| events_tx.lock().unwrap().send(event).unwrap())
+        .build()
+        .unwrap();
+    let release = saturate(&pool);
+
+    let handle = pool.submit(|| 7).unwrap();
+    assert_eq!(handle.join(), Ok(7));
+    let caller_events: Vec<PoolEvent> = events_rx
+        .try_iter()
+        .filter(|event| match event {
+            PoolEvent::JobStarted { worker } | PoolEvent::JobFinished { worker, .. } => *worker == CALLER_RUNS_WORKER,
+            _ => false,
+        })
+        .collect();
+    assert_eq!(caller_events.len(), 2);
+
+    drop(release);
+    assert!(pool.wait_idle_timeout(Duration::from_secs(5)));
+    assert_eq!(pool.stats().completed, 3);
+}
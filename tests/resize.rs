@@ -0,0 +1,57 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_threadpool::{PoolStats, ThreadPool};
+
+/// Poll the pool's stats until `done` accepts them, panics after a few seconds
+fn wait_for_stats<F>(pool: &ThreadPool, done: F) -> PoolStats where F: Fn(&PoolStats) -> bool, {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let stats = pool.stats();
+        if done(&stats) {
+            return stats;
+        }
+        assert!(Instant::now() < deadline, "stats never settled: {:?}", stats);
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn retired_workers_leave_stats() {
+    let pool = ThreadPool::new(4).unwrap();
+    for _ in 0..20 {
+        pool.execute(|| {}).unwrap();
+    }
+    pool.wait_idle();
+    pool.set_num_threads(1).unwrap();
+    let stats = wait_for_stats(&pool, |stats| stats.workers.len() == 1);
+    // Jobs run by the retired workers still count
+    assert_eq!(stats.completed, 20);
+    assert_eq!(stats.submitted, 20);
+
+    pool.set_num_threads(3).unwrap();
+    wait_for_stats(&pool, |stats| stats.workers.len() == 3);
+}
+
+#[test]
+fn replaced_workers_leave_stats_once_their_job_returns() {
+    let pool = ThreadPool::builder()
+        .num_threads(2)
+        .job_timeout(Duration::from_millis(10))
+        .replace_timed_out_workers(true)
+        .build()
+        .unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let handle = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+    // The hung worker and its replacement are both listed while the job runs
+    wait_for_stats(&pool, |stats| stats.workers.len() == 3);
+    drop(release_tx);
+    assert!(handle.join().is_err());
+    wait_for_stats(&pool, |stats| stats.workers.len() == 2);
+    assert_eq!(pool.num_threads(), 2);
+}
@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use rust_threadpool::{RejectionPolicy, ScheduledThreadPool, ThreadPool};
+
+#[test]
+fn cancel_drops_pending_entries() {
+    let pool = ScheduledThreadPool::new(1).unwrap();
+    let captured = Arc::new(());
+    let handles: Vec<_> = (0..1000)
+        .map(|_| {
+            let captured = Arc::clone(&captured);
+            pool.schedule(Duration::from_secs(3600), move || drop(captured))
+        })
+        .collect();
+    assert_eq!(Arc::strong_count(&captured), 1001);
+    for handle in &handles {
+        handle.cancel();
+    }
+    assert_eq!(Arc::strong_count(&captured), 1);
+    assert!(format!("{:?}", pool).contains("pending: 0"));
+}
+
+#[test]
+fn cancelled_one_shot_is_done() {
+    let pool = ScheduledThreadPool::new(1).unwrap();
+    let ran = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&ran);
+    let handle = pool.schedule(Duration::from_millis(20), move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    assert!(!handle.is_done());
+    handle.cancel();
+    assert!(handle.is_cancelled());
+    assert!(handle.is_done());
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn cancelled_periodic_job_stops_repeating() {
+    let pool = ScheduledThreadPool::new(1).unwrap();
+    let ran = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&ran);
+    let handle = pool.schedule_at_fixed_rate(Duration::ZERO, Duration::from_millis(5), move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    while ran.load(Ordering::SeqCst) < 2 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    handle.cancel();
+    assert!(handle.is_done());
+    // A run already queued on the pool may still be finishing
+    thread::sleep(Duration::from_millis(20));
+    let runs = ran.load(Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(ran.load(Ordering::SeqCst), runs);
+    assert!(format!("{:?}", pool).contains("pending: 0"));
+}
+
+#[test]
+fn pools_that_would_stall_the_timer_thread_are_refused() {
+    let bounded = |policy| ThreadPool::builder().num_threads(1).queue_capacity(4).rejection_policy(policy).build().unwrap();
+    assert!(ScheduledThreadPool::with_pool(bounded(RejectionPolicy::Block)).is_err());
+    assert!(ScheduledThreadPool::with_pool(bounded(RejectionPolicy::CallerRuns)).is_err());
+    assert!(ScheduledThreadPool::with_pool(bounded(RejectionPolicy::Abort)).is_ok());
+    assert!(ScheduledThreadPool::with_pool(bounded(RejectionPolicy::DiscardOldest)).is_ok());
+
+    // An unbounded queue never applies its policy
+    let unbounded = ThreadPool::builder().num_threads(1).rejection_policy(RejectionPolicy::CallerRuns).build().unwrap();
+    let pool = ScheduledThreadPool::with_pool(unbounded).unwrap();
+    let (tx, rx) = mpsc::channel();
+    pool.schedule(Duration::from_millis(1), move || tx.send(thread::current().name().map(String::from)).unwrap());
+    let ran_on = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_ne!(ran_on.as_deref(), Some("scheduled-timer"));
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use common::{block_worker, panic_message};
+use rust_threadpool::{RejectionPolicy, ThreadPool};
+
+#[test]
+fn scope_runs_borrowing_jobs() {
+    let pool = ThreadPool::new(4).unwrap();
+    let mut values = vec![0; 16];
+    pool.scope(|scope| {
+        for (index, value) in values.iter_mut().enumerate() {
+            scope.spawn(move || *value = index * 2);
+        }
+    });
+    assert_eq!(values, (0..16).map(|index| index * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn panic_in_spawned_job_propagates_after_all_jobs_finish() {
+    let pool = ThreadPool::new(2).unwrap();
+    let finished = AtomicUsize::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.scope(|scope| {
+            scope.spawn(|| panic!("scoped boom"));
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    thread::sleep(Duration::from_millis(2));
+                    finished.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        })
+    }));
+    let payload = result.unwrap_err();
+    assert_eq!(panic_message(payload.as_ref()), "scoped boom");
+    assert_eq!(finished.load(Ordering::SeqCst), 8);
+    // The panic was caught by the scope, not by a worker
+    assert_eq!(pool.panic_count(), 0);
+}
+
+#[test]
+fn job_evicted_under_discard_oldest_fails_the_scope() {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::DiscardOldest)
+        .build()
+        .unwrap();
+    let release = block_worker(&pool);
+    let first = AtomicUsize::new(0);
+    let second = AtomicUsize::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.scope(|scope| {
+            scope.spawn(|| {
+                first.fetch_add(1, Ordering::SeqCst);
+            });
+            // Evicts the first job from the full queue
+            scope.spawn(|| {
+                second.fetch_add(1, Ordering::SeqCst);
+            });
+            drop(release);
+        })
+    }));
+    let payload = result.unwrap_err();
+    assert_eq!(panic_message(payload.as_ref()), "scoped job was discarded before it ran");
+    assert_eq!(first.load(Ordering::SeqCst), 0);
+    assert_eq!(second.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn shutdown_now_while_scope_waits_drops_queued_jobs() {
+    let pool = ThreadPool::new(1).unwrap();
+    let release = block_worker(&pool);
+    let ran = AtomicUsize::new(0);
+    thread::scope(|threads| {
+        threads.spawn(|| {
+            while pool.stats().queued == 0 {
+                thread::yield_now();
+            }
+            // Dropping the drained jobs completes the scope without running them
+            assert_eq!(pool.shutdown_now().len(), 1);
+            drop(release);
+        });
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                scope.spawn(|| {
+                    ran.fetch_add(1, Ordering::SeqCst);
+                });
+            })
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "scoped job was discarded before it ran");
+    });
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+    assert!(pool.await_termination(Duration::from_secs(5)));
+}
+
+#[test]
+fn shutdown_now_while_scope_waits_can_run_queued_jobs() {
+    let pool = ThreadPool::new(1).unwrap();
+    let release = block_worker(&pool);
+    let ran = AtomicUsize::new(0);
+    thread::scope(|threads| {
+        threads.spawn(|| {
+            while pool.stats().queued == 0 {
+                thread::yield_now();
+            }
+            // The scope is still waiting, so the borrows of the drained job are alive
+            for job in pool.shutdown_now() {
+                job();
+            }
+            drop(release);
+        });
+        pool.scope(|scope| {
+            scope.spawn(|| {
+                ran.fetch_add(1, Ordering::SeqCst);
+            });
+        });
+    });
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+    assert!(pool.await_termination(Duration::from_secs(5)));
+}
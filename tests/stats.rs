@@ -0,0 +1,84 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use common::saturate;
+use rust_threadpool::{RejectionPolicy, ThreadPool};
+
+#[test]
+fn submitted_never_decreases_while_jobs_move_through_the_pool() {
+    let pool = Arc::new(ThreadPool::new(2).unwrap());
+    let done = Arc::new(AtomicBool::new(false));
+    let producer = {
+        let (pool, done) = (Arc::clone(&pool), Arc::clone(&done));
+        thread::spawn(move || {
+            for _ in 0..20_000 {
+                pool.execute(|| {}).unwrap();
+            }
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+    let mut last = 0;
+    while !done.load(Ordering::SeqCst) {
+        let submitted = pool.stats().submitted;
+        assert!(submitted >= last, "submitted went from {} to {}", last, submitted);
+        last = submitted;
+    }
+    producer.join().unwrap();
+    pool.wait_idle();
+    let stats = pool.stats();
+    assert_eq!((stats.submitted, stats.completed), (20_000, 20_000));
+}
+
+#[test]
+fn submitted_counts_caller_runs_jobs_but_not_rejected_ones() {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .queue_capacity(1)
+        .rejection_policy(RejectionPolicy::CallerRuns)
+        .build()
+        .unwrap();
+    let release = saturate(&pool);
+    pool.execute(|| {}).unwrap();
+    assert_eq!(pool.stats().submitted, 3);
+    drop(release);
+    pool.shutdown();
+    assert!(pool.execute(|| {}).is_err());
+    assert!(pool.await_termination(Duration::from_secs(5)));
+    assert_eq!(pool.stats().submitted, 3);
+}
+
+#[test]
+fn jobs_taken_by_shutdown_now_stay_submitted() {
+    let pool = ThreadPool::new(1).unwrap();
+    pool.pause();
+    for _ in 0..3 {
+        pool.execute(|| {}).unwrap();
+    }
+    assert_eq!(pool.shutdown_now().len(), 3);
+    let stats = pool.stats();
+    assert_eq!((stats.submitted, stats.completed, stats.queued), (3, 0, 0));
+}
+
+#[test]
+fn busy_time_is_off_unless_enabled() {
+    let pool = ThreadPool::new(1).unwrap();
+    // Polling the stats does not switch timing on
+    let _ = pool.stats();
+    pool.submit(|| thread::sleep(Duration::from_millis(5))).unwrap().join().unwrap();
+    assert!(pool.stats().workers.iter().all(|worker| worker.busy_time.is_none()));
+
+    let pool = ThreadPool::builder().num_threads(2).busy_time(true).build().unwrap();
+    let handles: Vec<_> = (0..4)
+        .map(|_| pool.submit(|| thread::sleep(Duration::from_millis(10))).unwrap())
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    pool.wait_idle();
+    let busy: Duration = pool.stats().workers.iter().map(|worker| worker.busy_time.unwrap()).sum();
+    assert!(busy >= Duration::from_millis(40), "{:?}", busy);
+}
@@ -0,0 +1,112 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use rust_threadpool::{TaskError, ThreadPool};
+
+/// Wakes the thread blocked in `block_on`
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` to completion on the current thread, parking between polls
+fn block_on<F>(future: F) -> F::Output where F: Future, {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn join_returns_the_value_or_the_panic() {
+    let pool = ThreadPool::new(2).unwrap();
+    assert_eq!(pool.submit(|| 21 * 2).unwrap().join(), Ok(42));
+    let handle = pool.submit(|| -> i32 { panic!("boom") }).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::Panicked(String::from("boom"))));
+    // The pool keeps running after a panicking job
+    assert_eq!(pool.submit(|| 3).unwrap().join(), Ok(3));
+    // The panic is counted just after the handle is resolved
+    pool.wait_idle();
+    assert_eq!(pool.panic_count(), 1);
+}
+
+#[test]
+fn try_join_does_not_block() {
+    let pool = ThreadPool::new(1).unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut handle = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+            1
+        })
+        .unwrap();
+    assert_eq!(handle.try_join(), None);
+    assert!(!handle.is_finished());
+    drop(release_tx);
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)), Some(Ok(1)));
+}
+
+#[test]
+fn join_timeout_gives_up_while_the_job_runs() {
+    let pool = ThreadPool::new(1).unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut handle = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+            1
+        })
+        .unwrap();
+    assert_eq!(handle.join_timeout(Duration::from_millis(20)), None);
+    drop(release_tx);
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)), Some(Ok(1)));
+    assert!(handle.is_finished());
+}
+
+#[test]
+#[should_panic(expected = "task result already taken")]
+fn joining_twice_panics() {
+    let pool = ThreadPool::new(1).unwrap();
+    let mut handle = pool.submit(|| 1).unwrap();
+    assert_eq!(handle.join_timeout(Duration::from_secs(5)), Some(Ok(1)));
+    let _ = handle.try_join();
+}
+
+#[test]
+fn handle_can_be_awaited() {
+    let pool = ThreadPool::new(1).unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let handle = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+            5
+        })
+        .unwrap();
+    // Released from another thread once the first poll has registered the waker
+    let releaser = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        drop(release_tx);
+    });
+    assert_eq!(block_on(handle), Ok(5));
+    releaser.join().unwrap();
+}
+
+#[test]
+fn discarded_job_resolves_its_handle() {
+    let pool = ThreadPool::new(1).unwrap();
+    pool.pause();
+    let handle = pool.submit(|| 1).unwrap();
+    drop(pool.shutdown_now());
+    assert_eq!(handle.join(), Err(TaskError::Discarded));
+}
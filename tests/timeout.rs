@@ -0,0 +1,124 @@
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_threadpool::{CancellationToken, TaskError, ThreadPool};
+
+/// Spin until `token` is cancelled
+fn wait_for_cancel(token: &CancellationToken) {
+    while !token.is_cancelled() {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn job_within_its_timeout_keeps_its_result() {
+    let pool = ThreadPool::builder().num_threads(1).job_timeout(Duration::from_secs(5)).build().unwrap();
+    assert_eq!(pool.submit(|| 1).unwrap().join(), Ok(1));
+    assert_eq!(pool.submit_with_timeout(Duration::from_secs(5), |_| 2).unwrap().join(), Ok(2));
+}
+
+#[test]
+fn default_timeout_resolves_the_handle_and_cancels_the_token() {
+    let pool = ThreadPool::builder().num_threads(1).job_timeout(Duration::from_millis(20)).build().unwrap();
+    let (saw_cancel_tx, saw_cancel_rx) = mpsc::channel();
+    let handle = pool
+        .submit_with_token(CancellationToken::new(), move |token| {
+            wait_for_cancel(token);
+            saw_cancel_tx.send(()).unwrap();
+        })
+        .unwrap();
+    assert_eq!(handle.join(), Err(TaskError::TimedOut));
+    saw_cancel_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn per_job_timeout_overrides_the_default() {
+    let pool = ThreadPool::builder().num_threads(1).job_timeout(Duration::from_secs(60)).build().unwrap();
+    let start = Instant::now();
+    let handle = pool.submit_with_timeout(Duration::from_millis(10), wait_for_cancel).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::TimedOut));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // Also on a pool without a default timeout
+    let pool = ThreadPool::new(1).unwrap();
+    let handle = pool.submit_with_timeout(Duration::from_millis(10), wait_for_cancel).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::TimedOut));
+}
+
+#[test]
+fn on_timeout_hook_gets_the_worker_and_run_time() {
+    let (seen_tx, seen_rx) = mpsc::channel();
+    let seen_tx = Mutex::new(seen_tx);
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .job_timeout(Duration::from_millis(20))
+        .on_timeout(move |worker, elapsed| seen_tx.lock().unwrap().send((worker, elapsed)).unwrap())
+        .build()
+        .unwrap();
+    let handle = pool.submit_with_token(CancellationToken::new(), wait_for_cancel).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::TimedOut));
+    // The hook runs just after the handle is resolved
+    let (worker, elapsed) = seen_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(worker, 0);
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(seen_rx.recv_timeout(Duration::from_millis(20)).is_err());
+}
+
+#[test]
+fn timed_out_worker_keeps_its_thread_without_replacement() {
+    let pool = ThreadPool::builder().num_threads(1).job_timeout(Duration::from_millis(10)).build().unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let hung = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+    assert_eq!(hung.join(), Err(TaskError::TimedOut));
+    let mut next = pool.submit(|| 3).unwrap();
+    assert_eq!(next.join_timeout(Duration::from_millis(50)), None);
+    drop(release_tx);
+    assert_eq!(next.join_timeout(Duration::from_secs(5)), Some(Ok(3)));
+}
+
+#[test]
+fn replaced_worker_lets_the_pool_keep_going() {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .job_timeout(Duration::from_millis(10))
+        .replace_timed_out_workers(true)
+        .build()
+        .unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let hung = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+    assert_eq!(hung.join(), Err(TaskError::TimedOut));
+    let mut next = pool.submit(|| 3).unwrap();
+    assert_eq!(next.join_timeout(Duration::from_secs(5)), Some(Ok(3)));
+    assert_eq!(pool.num_threads(), 1);
+    drop(release_tx);
+}
+
+#[test]
+fn dropping_the_pool_does_not_wait_for_a_hung_job() {
+    let pool = ThreadPool::builder()
+        .num_threads(1)
+        .job_timeout(Duration::from_millis(10))
+        .replace_timed_out_workers(true)
+        .build()
+        .unwrap();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let hung = pool
+        .submit(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+    assert_eq!(hung.join(), Err(TaskError::TimedOut));
+    let start = Instant::now();
+    drop(pool);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    drop(release_tx);
+}
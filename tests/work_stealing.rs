@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Barrier};
+use std::time::Duration;
+
+use rust_threadpool::{TaskError, WorkStealingPool};
+
+fn spawn_tree(pool: &Arc<WorkStealingPool>, count: &Arc<AtomicUsize>, depth: u32) {
+    count.fetch_add(1, Ordering::SeqCst);
+    if depth == 0 {
+        return;
+    }
+    for _ in 0..2 {
+        let (inner_pool, count) = (Arc::clone(pool), Arc::clone(count));
+        pool.execute(move || spawn_tree(&inner_pool, &count, depth - 1)).unwrap();
+    }
+}
+
+#[test]
+fn zero_workers_is_rejected() {
+    assert!(WorkStealingPool::new(0).is_err());
+    assert_eq!(WorkStealingPool::new(3).unwrap().num_threads(), 3);
+}
+
+#[test]
+fn jobs_from_outside_the_pool_all_run() {
+    let pool = WorkStealingPool::new(4).unwrap();
+    let handles: Vec<_> = (0..1000i64).map(|i| pool.submit(move || i).unwrap()).collect();
+    assert_eq!(handles.into_iter().map(|handle| handle.join().unwrap()).sum::<i64>(), 499_500);
+}
+
+#[test]
+fn jobs_spawned_by_workers_all_run() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let pool = Arc::new(WorkStealingPool::new(3).unwrap());
+    spawn_tree(&pool, &count, 10);
+    // Every job holds a reference to the pool until it returns, and the last
+    // reference must not be dropped on a worker
+    while Arc::strong_count(&pool) > 1 {
+        std::thread::yield_now();
+    }
+    assert_eq!(count.load(Ordering::SeqCst), 2047);
+}
+
+#[test]
+fn idle_workers_steal_from_a_busy_worker() {
+    let pool = Arc::new(WorkStealingPool::new(3).unwrap());
+    let (done_tx, done_rx) = mpsc::channel();
+    let inner_pool = Arc::clone(&pool);
+    pool.execute(move || {
+        // Both jobs land in this worker's own deque, they can only reach the
+        // barrier if the other workers steal them while this one waits
+        let barrier = Arc::new(Barrier::new(3));
+        for _ in 0..2 {
+            let barrier = Arc::clone(&barrier);
+            inner_pool
+                .execute(move || {
+                    barrier.wait();
+                })
+                .unwrap();
+        }
+        barrier.wait();
+        done_tx.send(()).unwrap();
+    })
+    .unwrap();
+    done_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    while Arc::strong_count(&pool) > 1 {
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn panics_are_counted_and_reported() {
+    let pool = WorkStealingPool::new(2).unwrap();
+    pool.execute(|| panic!("boom")).unwrap();
+    let handle = pool.submit(|| -> i32 { panic!("boom") }).unwrap();
+    assert_eq!(handle.join(), Err(TaskError::Panicked(String::from("boom"))));
+    assert_eq!(pool.submit(|| 1).unwrap().join(), Ok(1));
+    // The handle resolves just before the worker records the panic
+    while pool.panic_count() < 2 {
+        std::thread::yield_now();
+    }
+}
+
+#[test]
+fn drop_runs_queued_jobs() {
+    let ran = Arc::new(AtomicUsize::new(0));
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    {
+        let pool = WorkStealingPool::new(1).unwrap();
+        pool.execute(move || {
+            let _ = release_rx.recv();
+        })
+        .unwrap();
+        for _ in 0..5 {
+            let ran = Arc::clone(&ran);
+            pool.execute(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        drop(release_tx);
+    }
+    assert_eq!(ran.load(Ordering::SeqCst), 5);
+}